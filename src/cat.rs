@@ -0,0 +1,84 @@
+use std::ffi::OsString;
+use std::io::Write;
+
+use crate::{open_file, Decompress, Error, LineReader, Output, Result};
+use clap::Parser;
+
+#[derive(Parser, Debug)]
+#[clap(
+    name = "cat",
+    author = "protium",
+    version = "0.1.0",
+    about = "Concatenates files"
+)]
+pub struct Args {
+    #[clap(name = "files", default_value = "-", help = "Input file(s)")]
+    pub files: Vec<String>,
+
+    #[clap(
+        short,
+        long = "number",
+        help = "Number lines",
+        conflicts_with = "nonblank"
+    )]
+    pub number_lines: bool,
+
+    #[clap(
+        name = "nonblank",
+        short = 'b',
+        long = "number-nonblank",
+        help = "Number non-blank lines"
+    )]
+    pub number_nonblank_lines: bool,
+
+    #[command(flatten)]
+    pub decompress: Decompress,
+}
+
+pub fn run(args: Args) -> Result<()> {
+    let mut out = Output::new();
+    let mut failed = false;
+    for f in &args.files {
+        if let Err(e) = cat(f, &args, &mut out) {
+            eprintln!("{e}");
+            failed = true;
+        }
+    }
+
+    out.flush()
+        .map_err(|e| Error::io("cat", "standard output", e))?;
+
+    if failed {
+        return Err(Error::Status(1));
+    }
+
+    Ok(())
+}
+
+fn cat(filename: &str, args: &Args, out: &mut Output) -> Result<()> {
+    let io_err = |e| Error::io("cat", filename, e);
+    let out_err = |e| Error::io("cat", "standard output", e);
+    let mut content =
+        LineReader::new(open_file(filename, args.decompress.enabled()).map_err(io_err)?);
+    let mut n = 0;
+    while let Some(line) = content.next_line().map_err(io_err)? {
+        let number =
+            args.number_lines || (args.number_nonblank_lines && line != b"\n" && line != b"\r\n");
+        if number {
+            n += 1;
+            write!(out, "{:6}\t", n).map_err(out_err)?;
+        }
+
+        out.write_all(line).map_err(out_err)?;
+    }
+
+    Ok(())
+}
+
+pub fn get_args<I, T>(args: I) -> Result<Args>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    Args::try_parse_from(args).map_err(|e| Error::args("cat", e))
+}
@@ -0,0 +1,167 @@
+use crate::{chomp, open_file, Error, LineReader, Output, Result, Terminator};
+use clap::Parser;
+use std::cmp::Ordering::{Equal, Greater, Less};
+use std::ffi::OsString;
+use std::io::Write;
+
+#[derive(Parser, Debug)]
+#[command(
+    name = "comm",
+    author = "protium",
+    version = "0.1.0",
+    about = "comm compares two sorted files line by line."
+)]
+pub struct Args {
+    #[arg(value_name = "FILE 1", required = true)]
+    pub file_1: String,
+    #[arg(value_name = "FILE 2", required = true)]
+    pub file_2: String,
+
+    #[arg(
+        name = "ignore-case",
+        short = 'i',
+        long = "ignore-case",
+        help = "Ignore case distinctions"
+    )]
+    pub ignore_case: bool,
+
+    #[arg(
+        short = '1',
+        help = "suppress column 1 (lines unique to FILE1)",
+        default_value_t = false
+    )]
+    pub col_1: bool,
+
+    #[arg(
+        short = '2',
+        help = "suppress column 2 (lines unique to FILE1)",
+        default_value_t = false
+    )]
+    pub col_2: bool,
+
+    #[arg(
+        short = '3',
+        help = "suppress column 3 (lines unique to FILE1)",
+        default_value_t = false
+    )]
+    pub col_3: bool,
+
+    #[arg(
+        short = 'd',
+        long = "delimiter",
+        help = "output delimiter",
+        default_value = "\t"
+    )]
+    pub delimiter: String,
+
+    #[command(flatten)]
+    pub terminator: Terminator,
+}
+
+pub fn run(args: Args) -> Result<()> {
+    let file1 = &args.file_1;
+    let file2 = &args.file_2;
+    if file1 == "-" && file2 == "-" {
+        return Err(Error::usage("comm", "both input files cannot be STDIN"));
+    }
+
+    let delim = args.terminator.byte();
+    let case = |line: &[u8]| {
+        let line = chomp(line, delim);
+        if !args.ignore_case {
+            return line.to_vec();
+        }
+
+        match std::str::from_utf8(line) {
+            Ok(s) => s.to_lowercase().into_bytes(),
+            Err(_) => line.to_ascii_lowercase(),
+        }
+    };
+
+    let mut lines1 = LineReader::with_delimiter(
+        open_file(file1, false).map_err(|e| Error::io("comm", file1, e))?,
+        delim,
+    );
+    let mut lines2 = LineReader::with_delimiter(
+        open_file(file2, false).map_err(|e| Error::io("comm", file2, e))?,
+        delim,
+    );
+    let mut next1 = || -> Result<Option<Vec<u8>>> {
+        let line = lines1
+            .next_line()
+            .map_err(|e| Error::io("comm", file1, e))?;
+        Ok(line.map(case))
+    };
+    let mut next2 = || -> Result<Option<Vec<u8>>> {
+        let line = lines2
+            .next_line()
+            .map_err(|e| Error::io("comm", file2, e))?;
+        Ok(line.map(case))
+    };
+
+    let mut out = Output::new();
+    let mut print = |column: usize, line: &[u8]| -> Result<()> {
+        (1..column)
+            .try_for_each(|_| out.write_all(args.delimiter.as_bytes()))
+            .and_then(|_| out.write_all(line))
+            .and_then(|_| out.write_all(&[delim]))
+            .map_err(|e| Error::io("comm", "standard output", e))
+    };
+
+    let mut line1 = next1()?;
+    let mut line2 = next2()?;
+
+    while line1.is_some() || line2.is_some() {
+        match (&line1, &line2) {
+            (Some(a), Some(b)) => match a.cmp(b) {
+                Less => {
+                    if !args.col_1 {
+                        print(1, a)?;
+                    }
+                    line1 = next1()?;
+                }
+                Greater => {
+                    if !args.col_2 {
+                        print(2, b)?;
+                    }
+                    line2 = next2()?;
+                }
+                Equal => {
+                    if !args.col_3 {
+                        print(3, a)?;
+                    }
+                    line1 = next1()?;
+                    line2 = next2()?;
+                }
+            },
+
+            (Some(line), None) => {
+                if !args.col_1 {
+                    print(1, line)?;
+                }
+                line1 = next1()?;
+            }
+
+            (None, Some(line)) => {
+                if !args.col_2 {
+                    print(2, line)?;
+                }
+                line2 = next2()?;
+            }
+
+            _ => (),
+        }
+    }
+
+    out.flush()
+        .map_err(|e| Error::io("comm", "standard output", e))?;
+    Ok(())
+}
+
+pub fn get_args<I, T>(args: I) -> Result<Args>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    Args::try_parse_from(args).map_err(|e| Error::args("comm", e))
+}
@@ -0,0 +1,412 @@
+use crate::{chomp, open_file, Decompress, Error, LineReader, Output, Result, Terminator};
+use clap::Parser;
+use csv::{ByteRecord, ReaderBuilder, WriterBuilder};
+use std::ffi::OsString;
+use std::io::{self, BufRead, Write};
+use std::ops::Range;
+
+pub type Ranges = Vec<Range<usize>>;
+
+/// What a `Selector` picks from every line
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Mode {
+    Bytes,
+    Chars,
+    Fields,
+}
+
+#[derive(Parser, Debug)]
+#[command(
+    name = "cut",
+    author = "protium",
+    version = "0.1.0",
+    about = "Cut bytes, charachters, or fields from files. Lists are zero indexed"
+)]
+pub struct Args {
+    #[arg(
+        name = "files",
+        value_name = "FILES",
+        default_value = "-",
+        help = "Input file(s)"
+    )]
+    pub files: Vec<String>,
+
+    #[arg(
+        name = "bytes",
+        value_name = "BYTES",
+        short = 'b',
+        long = "bytes",
+        help = "Select only these bytes. Zero based",
+        conflicts_with_all = &["charachters", "fields"],
+        value_parser = parse_ranges,
+    )]
+    pub bytes: Option<Ranges>,
+
+    // E.g. -c 1,3,5-7.
+    #[arg(
+        name = "charachters",
+        value_name = "CHARS",
+        short = 'c',
+        long = "charachters",
+        help = "Select only these charachters. Zero based",
+        conflicts_with_all = &["bytes", "fields"],
+        value_parser = parse_ranges,
+    )]
+    pub chars: Option<Ranges>,
+
+    #[arg(
+        name = "fields",
+        value_name = "FIELDS",
+        short = 'f',
+        long = "fields",
+        help = "Select only these fields. Zero based",
+        conflicts_with_all = &["bytes", "charachters"],
+        value_parser = parse_ranges,
+    )]
+    pub fields: Option<Ranges>,
+
+    // NOTE: should respect escaped delimiters
+    #[arg(
+        name = "delimiters",
+        value_name = "DELIM",
+        short = 'd',
+        long = "delimiter",
+        default_value = "\t",
+        value_parser = parse_delimiter,
+        help = "Use DELIM instead of TAB for field delimiter"
+    )]
+    pub delim: u8,
+
+    #[command(flatten)]
+    pub decompress: Decompress,
+
+    #[command(flatten)]
+    pub terminator: Terminator,
+}
+
+fn parse_delimiter(delim: &str) -> std::result::Result<u8, String> {
+    let delim_bytes = delim.as_bytes();
+    if delim_bytes.len() != 1 {
+        return Err("--delim must be a single byte".into());
+    }
+
+    Ok(delim_bytes[0])
+}
+
+/// Parses a zero based list such as "0,3,5-7"
+pub fn parse_ranges(range: &str) -> std::result::Result<Ranges, String> {
+    let range_expr = regex::Regex::new(r"^(\d+)(-\d+)?$").unwrap();
+    range
+        .split(',')
+        .map(|r| {
+            range_expr
+                .captures(r)
+                .ok_or_else(|| format!("invalid list value: {:?}", r))
+                .and_then(|captures| {
+                    let first = captures[1].to_string();
+                    let first = first
+                        .parse::<usize>()
+                        .map_err(|_| format!("invalid value: \"{}\"", first))?;
+
+                    if captures.get(2).is_none() {
+                        return Ok(first..first + 1);
+                    }
+
+                    let second = captures[2].to_string();
+                    let second = second[1..] // remove leading dash
+                        .parse::<usize>()
+                        .map_err(|_| format!("invalid value: \"{}\"", second))?;
+
+                    if first >= second {
+                        return Err("first number in range must be lower than second".into());
+                    }
+
+                    Ok(first..second)
+                })
+        })
+        .collect::<std::result::Result<_, _>>()
+}
+
+/// Splits `line` into characters. Bytes that are not valid UTF-8 count as
+/// one character each, so they are passed through as they are
+fn chars(line: &[u8]) -> Vec<&[u8]> {
+    let mut chars = Vec::new();
+    for chunk in line.utf8_chunks() {
+        let valid = chunk.valid();
+        chars.extend(
+            valid
+                .char_indices()
+                .map(|(i, c)| &valid.as_bytes()[i..i + c.len_utf8()]),
+        );
+        chars.extend(chunk.invalid().chunks(1));
+    }
+
+    chars
+}
+
+fn extract_chars(line: &[u8], chars_ranges: &[Range<usize>]) -> Vec<u8> {
+    chars(line)
+        .iter()
+        .enumerate()
+        .flat_map(|(i, c)| {
+            if chars_ranges.iter().any(|r| r.contains(&i)) {
+                Some(*c)
+            } else {
+                None
+            }
+        })
+        .flatten()
+        .copied()
+        .collect()
+}
+
+fn extract_bytes(line: &[u8], chars: &[Range<usize>]) -> Vec<u8> {
+    line.iter()
+        .enumerate()
+        .flat_map(|(i, c)| {
+            if chars.iter().any(|r| r.contains(&i)) {
+                Some(*c)
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+fn extract_fields<'a>(record: &'a ByteRecord, ranges: &[Range<usize>]) -> Vec<&'a [u8]> {
+    record
+        .iter()
+        .enumerate()
+        .filter_map(|(i, field)| {
+            if ranges.iter().any(|r| r.contains(&i)) {
+                Some(field)
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// Selects bytes, characters or fields from every line of an input. This
+/// is cut without the command line, it works on any `BufRead`
+#[derive(Clone, Debug)]
+pub struct Selector {
+    mode: Mode,
+    ranges: Ranges,
+    delimiter: u8,
+    terminator: u8,
+}
+
+impl Selector {
+    pub fn new(mode: Mode, ranges: Ranges) -> Self {
+        Self {
+            mode,
+            ranges,
+            delimiter: b'\t',
+            terminator: b'\n',
+        }
+    }
+
+    /// Field delimiter, TAB by default
+    pub fn delimiter(mut self, byte: u8) -> Self {
+        self.delimiter = byte;
+        self
+    }
+
+    /// Byte that ends every line, '\n' by default
+    pub fn terminator(mut self, byte: u8) -> Self {
+        self.terminator = byte;
+        self
+    }
+
+    /// Writes the selected part of every line of `input` to `out`
+    pub fn cut<R: BufRead, W: Write>(&self, input: R, mut out: W) -> io::Result<()> {
+        if self.mode == Mode::Fields {
+            let mut reader = ReaderBuilder::new()
+                .delimiter(self.delimiter)
+                .has_headers(false)
+                .terminator(csv::Terminator::Any(self.terminator))
+                .from_reader(input);
+
+            let mut writer = WriterBuilder::new()
+                .delimiter(self.delimiter)
+                .terminator(csv::Terminator::Any(self.terminator))
+                .from_writer(out);
+
+            for result in reader.byte_records() {
+                writer.write_record(extract_fields(&result?, &self.ranges))?;
+            }
+
+            return writer.flush();
+        }
+
+        let mut input = LineReader::with_delimiter(input, self.terminator);
+        while let Some(line) = input.next_line()? {
+            let line = chomp(line, self.terminator);
+            let selected = match self.mode {
+                Mode::Bytes => extract_bytes(line, &self.ranges),
+                _ => extract_chars(line, &self.ranges),
+            };
+
+            out.write_all(&selected)?;
+            out.write_all(&[self.terminator])?;
+        }
+
+        Ok(())
+    }
+}
+
+pub fn run(args: Args) -> Result<()> {
+    let (ranges, mode) = if let Some(ranges) = &args.bytes {
+        (ranges, Mode::Bytes)
+    } else if let Some(ranges) = &args.chars {
+        (ranges, Mode::Chars)
+    } else if let Some(ranges) = &args.fields {
+        (ranges, Mode::Fields)
+    } else {
+        unreachable!()
+    };
+
+    let selector = Selector::new(mode, ranges.clone())
+        .delimiter(args.delim)
+        .terminator(args.terminator.byte());
+    let mut out = Output::new();
+    let mut failed = false;
+    for (i, f) in args.files.iter().enumerate() {
+        if let Err(e) = cut(f, i > 0, &selector, &args, &mut out) {
+            eprintln!("{e}");
+            failed = true;
+        }
+    }
+
+    out.flush()
+        .map_err(|e| Error::io("cut", "standard output", e))?;
+
+    if failed {
+        return Err(Error::Status(1));
+    }
+
+    Ok(())
+}
+
+fn cut(
+    filename: &str,
+    separate: bool,
+    selector: &Selector,
+    args: &Args,
+    out: &mut Output,
+) -> Result<()> {
+    let io_err = |e| Error::io("cut", filename, e);
+    let content = open_file(filename, args.decompress.enabled()).map_err(io_err)?;
+    if separate {
+        writeln!(out).map_err(|e| Error::io("cut", "standard output", e))?;
+    }
+
+    selector.cut(content, out).map_err(io_err)
+}
+
+pub fn get_args<I, T>(args: I) -> Result<Args>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    let args = Args::try_parse_from(args).map_err(|e| Error::args("cut", e))?;
+
+    if args.bytes.is_none() && args.chars.is_none() && args.fields.is_none() {
+        return Err(Error::usage(
+            "cut",
+            "must have --fields, --bytes, or --chars",
+        ));
+    }
+
+    Ok(args)
+}
+
+#[cfg(test)]
+#[allow(clippy::single_range_in_vec_init)]
+mod unit_tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ranges() {
+        assert!(parse_ranges("").is_err());
+
+        let r = parse_ranges("+1");
+        assert!(r.is_err());
+        assert_eq!(r.unwrap_err().to_string(), "invalid list value: \"+1\"");
+
+        let r = parse_ranges("1-a");
+        assert!(r.is_err());
+        assert_eq!(r.unwrap_err().to_string(), "invalid list value: \"1-a\"");
+
+        let r = parse_ranges("1-1");
+        assert!(r.is_err());
+        assert_eq!(
+            r.unwrap_err().to_string(),
+            "first number in range must be lower than second"
+        );
+
+        assert_eq!(parse_ranges("0").unwrap(), vec![0..1]);
+        assert_eq!(parse_ranges("0,2").unwrap(), vec![0..1, 2..3]);
+        assert_eq!(parse_ranges("1-3").unwrap(), vec![1..3]);
+        assert_eq!(parse_ranges("0,6,2-5").unwrap(), vec![0..1, 6..7, 2..5]);
+    }
+
+    #[test]
+    fn test_extract_chars() {
+        assert_eq!(extract_chars(b"", &[0..1]), b"");
+        assert_eq!(extract_chars("á".as_bytes(), &[0..1]), "á".as_bytes());
+        assert_eq!(
+            extract_chars("ábc".as_bytes(), &[0..1, 1..2]),
+            "áb".as_bytes()
+        );
+        assert_eq!(extract_chars("ábc".as_bytes(), &[0..3]), "ábc".as_bytes());
+        assert_eq!(extract_chars("ábc".as_bytes(), &[2..3, 1..2]), b"bc");
+        assert_eq!(
+            extract_chars("ábc".as_bytes(), &[0..1, 1..2, 4..5]),
+            "áb".as_bytes()
+        );
+        assert_eq!(extract_chars(b"\xe1bc", &[0..2]), b"\xe1b");
+    }
+
+    #[test]
+    fn test_extract_bytes() {
+        assert_eq!(extract_bytes("á".as_bytes(), &[0..1]), b"\xc3");
+        assert_eq!(extract_bytes("ábc".as_bytes(), &[0..2]), "á".as_bytes());
+        assert_eq!(extract_bytes("ábc".as_bytes(), &[0..3]), "áb".as_bytes());
+        assert_eq!(extract_bytes("ábc".as_bytes(), &[0..4]), "ábc".as_bytes());
+        assert_eq!(extract_bytes("ábc".as_bytes(), &[3..4, 2..3]), b"bc");
+    }
+
+    #[test]
+    fn test_extract_fields() {
+        let rec = ByteRecord::from(vec!["Name", "Description", "Number"]);
+        assert_eq!(extract_fields(&rec, &[0..1]), vec![b"Name"]);
+        assert_eq!(extract_fields(&rec, &[1..2]), vec![b"Description"]);
+        assert_eq!(
+            extract_fields(&rec, &[1..2, 0..1]),
+            vec![&b"Name"[..], &b"Description"[..]]
+        );
+        assert_eq!(
+            extract_fields(&rec, &[2..3, 0..1]),
+            vec![&b"Name"[..], &b"Number"[..]]
+        );
+    }
+
+    #[test]
+    fn test_selector() {
+        let mut out = Vec::new();
+        Selector::new(Mode::Fields, vec![1..2])
+            .delimiter(b',')
+            .cut(&b"a,b,c\nd,e,f\n"[..], &mut out)
+            .unwrap();
+        assert_eq!(out, b"b\ne\n");
+
+        let mut out = Vec::new();
+        Selector::new(Mode::Chars, vec![0..2])
+            .terminator(b'\0')
+            .cut("ábc\0xyz\0".as_bytes(), &mut out)
+            .unwrap();
+        assert_eq!(out, "áb\0xy\0".as_bytes());
+    }
+}
@@ -0,0 +1,52 @@
+use std::{ffi::OsString, io::Write};
+
+use crate::{Error, Output, Result};
+
+#[derive(Debug)]
+pub struct Args {
+    pub txt: Vec<String>,
+    pub omit_newline: bool,
+}
+
+pub fn command() -> clap::Command {
+    clap::Command::new("echo")
+        .author("protium, ioprotium@gmail.com")
+        .version("0.1.0")
+        .about("Echoes arguments")
+        .arg(clap::Arg::new("txt").num_args(0..))
+        .arg(
+            clap::Arg::new("omit_newline")
+                .short('n')
+                .num_args(0)
+                .action(clap::ArgAction::SetTrue)
+                .required(false),
+        )
+}
+
+pub fn run(args: Args) -> Result<()> {
+    let ending = if args.omit_newline { "" } else { "\n" };
+    let mut out = Output::new();
+    write!(out, "{}{}", args.txt.join(" "), ending)
+        .and_then(|_| out.flush())
+        .map_err(|e| Error::io("echo", "standard output", e))
+}
+
+pub fn get_args<I, T>(args: I) -> Result<Args>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    let m = command()
+        .try_get_matches_from(args)
+        .map_err(|e| Error::args("echo", e))?;
+    let txt = if let Some(args) = m.get_many::<String>("txt") {
+        args.cloned().collect()
+    } else {
+        Vec::new()
+    };
+
+    Ok(Args {
+        txt,
+        omit_newline: m.get_flag("omit_newline"),
+    })
+}
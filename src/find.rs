@@ -0,0 +1,291 @@
+use std::{ffi::OsString, io::Write};
+
+use crate::{Error, Output, Result};
+use clap::{CommandFactory, Parser};
+use walkdir::WalkDir;
+
+mod action;
+mod expr;
+mod sys;
+
+pub use action::{Action, Context};
+pub use expr::{Expr, Setting, Traversal};
+
+#[derive(Debug, Copy, Clone)]
+pub enum EntryType {
+    File,
+    Directory,
+    Symlink,
+}
+
+impl std::str::FromStr for EntryType {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "f" => Ok(EntryType::File),
+            "d" => Ok(EntryType::Directory),
+            "l" => Ok(EntryType::Symlink),
+            _ => Err(format!("Invalid entry type: {}", s)),
+        }
+    }
+}
+
+const EXPRESSION_HELP: &str = "\
+Expression: from the first argument that starts with -, ( or !, or after `--`,
+tests and actions joined by operators, from the highest precedence to the lowest:
+  ( EXPR )         grouping
+  ! EXPR, -not     negation
+  EXPR -a EXPR     and, also implied by two adjacent tests
+  EXPR -o EXPR     or
+
+Tests, where +N means more than N and -N less than N:
+  -type LIST       file is of a type in the comma-separated LIST: [f, d, l]
+  -size [+-]N[bckMG]  size in units of 512 bytes or of the suffix, rounded up
+  -mtime, -atime, -ctime [+-]N  modified, accessed or changed N days ago
+  -mmin, -amin, -cmin [+-]N     the same in minutes
+  -newer FILE      modified more recently than FILE
+  -perm [-/]MODE   permission bits are exactly, all of or any of MODE
+  -user NAME, -group NAME, -uid [+-]N, -gid [+-]N  ownership
+  -links [+-]N     number of hard links
+  -empty           empty file or directory
+  -name GLOB       file name matches the shell pattern GLOB, -iname ignores case
+  -path GLOB       path matches GLOB, where * also matches /, also -wholename,
+                   -ipath and -iwholename
+  -regex REGEX     whole path matches REGEX, -iregex ignores case
+  -regextype TYPE  syntax of the next -regex: rust, posix-extended or egrep,
+                   which this find treats alike
+  REGEX            file name matches REGEX, after `--` or another test
+
+Actions, -print is implied when there are none:
+  -print, -print0  print the path and a newline or a NUL
+  -printf FORMAT   print FORMAT with directives such as %p, %f, %s, %m or %TY,
+                   times in UTC
+  -exec CMD ;      run CMD, where {} is the path, true if it succeeds
+  -exec CMD {} +   run CMD with as many paths at once as possible
+  -execdir CMD ;   the same from the directory of the file, also with +
+  -ok CMD ;        ask before running CMD, also -okdir
+  -delete          delete the file, searching directories depth first
+  -prune           do not descend into the directory, true
+
+Options, true wherever they are:
+  -mindepth N      apply the expression from depth N only, 1 skips the paths
+  -maxdepth N      descend at most N levels below the paths
+  -depth           visit the contents of directories before them
+  -xdev, -mount    stay on the file system of the paths";
+
+#[derive(Parser, Debug)]
+#[clap(
+    name = "find",
+    author = "protium",
+    version = "0.1.0",
+    about = "Search for files in directory hierarchy",
+    after_help = EXPRESSION_HELP
+)]
+pub struct Args {
+    #[arg(name = "depth", short, long, help = "Same as -maxdepth")]
+    pub depth: Option<usize>,
+
+    #[arg(
+        name = "L",
+        short = 'L',
+        help = "Follow symbolic links",
+        overrides_with_all = ["H", "P"]
+    )]
+    pub follow_links: bool,
+
+    #[arg(
+        name = "H",
+        short = 'H',
+        help = "Follow symbolic links given as paths only",
+        overrides_with_all = ["L", "P"]
+    )]
+    pub follow_root_links: bool,
+
+    #[arg(
+        name = "P",
+        short = 'P',
+        help = "Never follow symbolic links, the default",
+        overrides_with_all = ["L", "H"]
+    )]
+    pub no_follow: bool,
+
+    #[arg(
+        name = "sort",
+        short,
+        long,
+        help = "Visit the entries of every directory in lexicographic order"
+    )]
+    pub sort: bool,
+
+    #[arg(name = "type", short, long, help = "File is of type: [f, d, l]")]
+    pub entry_types: Vec<EntryType>,
+
+    #[arg(name = "paths", default_value = ".")]
+    pub paths: Vec<String>,
+
+    #[arg(
+        name = "expression",
+        value_name = "EXPRESSION",
+        help = "Tests and actions, see below. All files by default",
+        last = true,
+        allow_hyphen_values = true
+    )]
+    pub expression: Vec<String>,
+}
+
+pub fn run(args: Args) -> Result<()> {
+    let mut expr = Expr::parse(&args.expression)?;
+    if !args.entry_types.is_empty() {
+        expr = Expr::Type(args.entry_types.clone()).and(expr);
+    }
+    if !expr.has_action() {
+        expr = expr.and(Expr::Action(Action::Print(b'\n')));
+    }
+
+    let traversal = expr.traversal();
+    let mut context = Context::new(Output::new());
+    context.follow_root_links = args.follow_links || args.follow_root_links;
+    for path in &args.paths {
+        let mut walk = WalkDir::new(path)
+            .follow_links(args.follow_links)
+            .follow_root_links(args.follow_links || args.follow_root_links)
+            .same_file_system(traversal.same_file_system)
+            .contents_first(traversal.contents_first);
+        if let Some(depth) = traversal.min_depth {
+            walk = walk.min_depth(depth);
+        }
+        if let Some(depth) = traversal.max_depth.or(args.depth) {
+            walk = walk.max_depth(depth);
+        }
+        if args.sort {
+            walk = walk.sort_by(|a, b| a.file_name().cmp(b.file_name()));
+        }
+
+        let mut entries = walk.into_iter();
+        while let Some(entry) = entries.next() {
+            match entry {
+                Ok(entry) => {
+                    context.prune = false;
+                    expr.eval(&entry, &mut context)?;
+                    // A path may also be a followed symlink to a directory.
+                    // With -depth the contents came first, too late to skip
+                    let dir = entry.file_type().is_dir() || entry.depth() == 0;
+                    if context.prune && dir && !traversal.contents_first {
+                        entries.skip_current_dir();
+                    }
+                }
+                Err(e) => {
+                    let path = e
+                        .path()
+                        .map_or_else(|| path.clone(), |p| p.display().to_string());
+                    match e.loop_ancestor() {
+                        Some(ancestor) => eprintln!(
+                            "find: File system loop detected; '{path}' is part of \
+                             the same file system loop as '{}'.",
+                            ancestor.display()
+                        ),
+                        None => eprintln!("{}", Error::io("find", path, e.into())),
+                    }
+                    context.failed = true;
+                }
+            }
+        }
+    }
+
+    expr.finish(&mut context)?;
+    context
+        .out
+        .flush()
+        .map_err(|e| Error::io("find", "standard output", e))?;
+
+    if context.failed {
+        return Err(Error::Status(1));
+    }
+
+    Ok(())
+}
+
+pub fn get_args<I, T>(args: I) -> Result<Args>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    let args = split_expression(args.into_iter().map(Into::into).collect());
+    Args::try_parse_from(args).map_err(|e| Error::args("find", e))
+}
+
+/// Inserts `--` before the expression of `find [OPTIONS] [PATHS] EXPRESSION`,
+/// which starts at the first word other than an option that starts with -,
+/// ( or !, so that clap does not take tests such as `-name` for options
+fn split_expression(mut args: Vec<OsString>) -> Vec<OsString> {
+    let mut command = Args::command();
+    // Adds --help and --version
+    command.build();
+    let option = |word: &str| {
+        command.get_arguments().find(|arg| {
+            if let Some(long) = word.strip_prefix("--") {
+                arg.get_long() == Some(long)
+            } else {
+                word.len() == 2
+                    && word.starts_with('-')
+                    && arg.get_short().is_some_and(|short| word.ends_with(short))
+            }
+        })
+    };
+
+    let mut i = 1;
+    while let Some(word) = args.get(i) {
+        let word = word.to_string_lossy();
+        if word == "--" {
+            break;
+        }
+
+        if let Some(arg) = option(&word) {
+            if arg.get_action().takes_values() {
+                i += 1;
+            }
+        } else if word == "(" || word == "!" || (word.starts_with('-') && word.len() > 1) {
+            // Options with attached values, such as --depth=1, stay options
+            if !word.starts_with("--") || !word.contains('=') {
+                args.insert(i, "--".into());
+                break;
+            }
+        }
+        i += 1;
+    }
+
+    args
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    #[test]
+    fn test_split_expression() {
+        let split = |args: &[&str]| -> Vec<String> {
+            let args = args.iter().map(OsString::from).collect();
+            split_expression(args)
+                .into_iter()
+                .map(|arg| arg.into_string().unwrap())
+                .collect()
+        };
+
+        assert_eq!(split(&["find", "dir"]), ["find", "dir"]);
+        assert_eq!(
+            split(&["find", "-t", "f", "-d", "2", "a", "b", "-name", "*.rs"]),
+            ["find", "-t", "f", "-d", "2", "a", "b", "--", "-name", "*.rs"]
+        );
+        assert_eq!(
+            split(&["find", "--depth=1", "(", "-type", "d", ")"]),
+            ["find", "--depth=1", "--", "(", "-type", "d", ")"]
+        );
+        assert_eq!(split(&["find", "!", "x"]), ["find", "--", "!", "x"]);
+        assert_eq!(
+            split(&["find", "dir", "--", "a"]),
+            ["find", "dir", "--", "a"]
+        );
+        assert_eq!(split(&["find", "-", "-h"]), ["find", "-", "-h"]);
+    }
+}
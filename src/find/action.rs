@@ -0,0 +1,745 @@
+//! Actions of find expressions: printing entries, running commands on
+//! them and deleting them
+
+use std::{
+    env,
+    ffi::{OsStr, OsString},
+    fs, io,
+    io::{BufRead, Write},
+    mem,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    time::SystemTime,
+};
+
+use walkdir::DirEntry;
+
+use super::{
+    expr::{Candidate, Timestamp},
+    sys,
+};
+use crate::{pipe, Error, Result};
+
+/// Bytes of arguments and environment for one command of `-exec ... +`.
+/// Every system allows at least this much, as xargs assumes by default
+const ARG_MAX: usize = 128 * 1024;
+
+/// The state of a search that expressions read and update: where actions
+/// write and what they report back
+pub struct Context<W> {
+    pub out: W,
+    pub failed: bool,
+    /// -H or -L: symlinks given as paths stand for their targets
+    pub follow_root_links: bool,
+    /// Set by `-prune`: skip the contents of the current directory
+    pub prune: bool,
+}
+
+impl<W: Write> Context<W> {
+    pub fn new(out: W) -> Self {
+        Self {
+            out,
+            failed: false,
+            follow_root_links: false,
+            prune: false,
+        }
+    }
+
+    fn write(&mut self, buf: &[u8]) -> Result<()> {
+        self.out
+            .write_all(buf)
+            .map_err(|e| Error::io("find", "standard output", e))
+    }
+
+    /// Reports an error that does not stop the search
+    fn report(&mut self, error: Error) {
+        error.report();
+        self.failed = true;
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum Action {
+    /// `-print` and `-print0`: the path and a terminator
+    Print(u8),
+    Printf(Format),
+    Exec(Exec),
+    Delete,
+}
+
+impl Action {
+    pub(super) fn run<W: Write>(
+        &mut self,
+        candidate: &Candidate,
+        context: &mut Context<W>,
+    ) -> Result<bool> {
+        let entry = candidate.entry;
+        match self {
+            Self::Print(terminator) => {
+                context.write(entry.path().as_os_str().as_encoded_bytes())?;
+                context.write(&[*terminator])?;
+                Ok(true)
+            }
+            Self::Printf(format) => {
+                let mut buf = Vec::new();
+                format.render(candidate, &mut buf);
+                context.write(&buf)?;
+                Ok(true)
+            }
+            Self::Exec(exec) => exec.run(entry, context),
+            Self::Delete => Ok(delete(entry, context)),
+        }
+    }
+}
+
+fn delete<W: Write>(entry: &DirEntry, context: &mut Context<W>) -> bool {
+    // Like the other finds, never remove the current directory
+    if entry.path() == Path::new(".") {
+        return true;
+    }
+
+    let result = if entry.file_type().is_dir() {
+        fs::remove_dir(entry.path())
+    } else {
+        fs::remove_file(entry.path())
+    };
+
+    match result {
+        Ok(()) => true,
+        Err(e) => {
+            let path = format!("cannot delete '{}'", entry.path().display());
+            context.report(Error::io("find", path, e));
+            false
+        }
+    }
+}
+
+/// `-exec`, `-execdir`, `-ok` and `-okdir`
+#[derive(Clone, Debug)]
+pub struct Exec {
+    /// The command and its arguments, where `{}` stands for the path. The
+    /// paths follow the arguments with `{} +`
+    command: Vec<String>,
+    /// Run from the directory of the entry, with `./NAME` as its path
+    in_dir: bool,
+    /// Ask on standard error before every command
+    confirm: bool,
+    /// `{} +`: run as few commands as possible, with many paths
+    batched: bool,
+    pending: Batch,
+}
+
+/// Paths for the next command of `-exec ... {} +`
+#[derive(Clone, Debug, Default)]
+struct Batch {
+    dir: Option<PathBuf>,
+    paths: Vec<OsString>,
+    /// Bytes of the command line so far, as counted against `ARG_MAX`
+    size: usize,
+}
+
+impl Exec {
+    pub(super) fn new<S: AsRef<str>>(
+        command: &[S],
+        in_dir: bool,
+        confirm: bool,
+        batched: bool,
+    ) -> Self {
+        Self {
+            command: command.iter().map(|arg| arg.as_ref().to_string()).collect(),
+            in_dir,
+            confirm,
+            batched,
+            pending: Batch::default(),
+        }
+    }
+
+    fn run<W: Write>(&mut self, entry: &DirEntry, context: &mut Context<W>) -> Result<bool> {
+        let (dir, path) = if self.in_dir {
+            let dir = entry
+                .path()
+                .parent()
+                .filter(|dir| !dir.as_os_str().is_empty())
+                .unwrap_or(Path::new("."));
+            let mut name = OsString::from("./");
+            name.push(entry.file_name());
+            (Some(dir.to_path_buf()), name)
+        } else {
+            (None, entry.path().as_os_str().to_owned())
+        };
+
+        if !self.batched {
+            let args = self
+                .command
+                .iter()
+                .map(|arg| substitute(arg, &path))
+                .collect();
+            return self.execute(args, dir.as_deref(), context);
+        }
+
+        let size = arg_size(&path);
+        if !self.pending.paths.is_empty()
+            && (self.pending.dir != dir || self.pending.size + size > ARG_MAX)
+        {
+            self.flush(context)?;
+        }
+
+        if self.pending.paths.is_empty() {
+            let environment: usize = env::vars_os()
+                .map(|(key, value)| arg_size(&key) + arg_size(&value))
+                .sum();
+            self.pending.dir = dir;
+            self.pending.size = environment + self.command.iter().map(arg_size).sum::<usize>();
+        }
+        self.pending.paths.push(path);
+        self.pending.size += size;
+
+        Ok(true)
+    }
+
+    /// Runs the command on the paths held so far by `{} +`
+    pub(super) fn flush<W: Write>(&mut self, context: &mut Context<W>) -> Result<()> {
+        if self.pending.paths.is_empty() {
+            return Ok(());
+        }
+
+        let batch = mem::take(&mut self.pending);
+        let args = self
+            .command
+            .iter()
+            .map(OsString::from)
+            .chain(batch.paths)
+            .collect();
+        self.execute(args, batch.dir.as_deref(), context)?;
+
+        Ok(())
+    }
+
+    /// Runs `args` on our standard output, flushed first so that it keeps
+    /// its place among the other output. In a pipeline stage, whose
+    /// output is not a file, the output of the command is copied there.
+    /// Whether it exited successfully
+    fn execute<W: Write>(
+        &self,
+        args: Vec<OsString>,
+        dir: Option<&Path>,
+        context: &mut Context<W>,
+    ) -> Result<bool> {
+        if self.confirm && !confirm(&args, context)? {
+            return Ok(false);
+        }
+
+        context
+            .out
+            .flush()
+            .map_err(|e| Error::io("find", "standard output", e))?;
+
+        let mut command = Command::new(&args[0]);
+        command.args(&args[1..]);
+        if pipe::is_stage() {
+            command.stdout(Stdio::piped());
+        }
+        if let Some(dir) = dir {
+            command.current_dir(dir);
+        }
+        if self.confirm {
+            // Standard input answers the questions
+            command.stdin(Stdio::null());
+        }
+
+        let mut child = match command.spawn() {
+            Ok(child) => child,
+            Err(e) => {
+                context.report(Error::io("find", args[0].to_string_lossy(), e));
+                return Ok(false);
+            }
+        };
+
+        let copied = match child.stdout.as_mut() {
+            Some(stdout) => io::copy(stdout, &mut context.out).map(|_| ()),
+            None => Ok(()),
+        };
+        let status = child.wait();
+        copied.map_err(|e| Error::io("find", "standard output", e))?;
+
+        match status {
+            Ok(status) => {
+                // As for xargs, a failed command of {} + fails find
+                if self.batched && !status.success() {
+                    context.failed = true;
+                }
+                Ok(status.success())
+            }
+            Err(e) => {
+                context.report(Error::io("find", args[0].to_string_lossy(), e));
+                Ok(false)
+            }
+        }
+    }
+}
+
+/// Asks whether to run `args`, as `-ok` does
+fn confirm<W: Write>(args: &[OsString], context: &mut Context<W>) -> Result<bool> {
+    context
+        .out
+        .flush()
+        .map_err(|e| Error::io("find", "standard output", e))?;
+
+    let line: Vec<_> = args.iter().map(|arg| arg.to_string_lossy()).collect();
+    eprint!("< {} > ? ", line.join(" "));
+
+    let mut answer = String::new();
+    io::stdin()
+        .lock()
+        .read_line(&mut answer)
+        .map_err(|e| Error::io("find", "standard input", e))?;
+    Ok(answer.trim_start().starts_with(['y', 'Y']))
+}
+
+/// `arg` with every `{}` replaced by `path`
+fn substitute(arg: &str, path: &OsStr) -> OsString {
+    let mut result = OsString::new();
+    for (i, part) in arg.split("{}").enumerate() {
+        if i > 0 {
+            result.push(path);
+        }
+        result.push(part);
+    }
+
+    result
+}
+
+/// Bytes that `arg` takes on a command line: itself, its NUL and a pointer
+fn arg_size(arg: impl AsRef<OsStr>) -> usize {
+    arg.as_ref().len() + 1 + mem::size_of::<usize>()
+}
+
+/// The format of `-printf`
+#[derive(Clone, Debug)]
+pub struct Format(Vec<Segment>);
+
+#[derive(Clone, Debug)]
+enum Segment {
+    Literal(Vec<u8>),
+    /// A `%` directive, padded to `width` on the left, or on the right
+    /// with `-`. `time` is the conversion of `%A`, `%C` and `%T`
+    Field {
+        directive: u8,
+        time: Option<u8>,
+        width: usize,
+        left: bool,
+    },
+    /// `\c`: the end of the output
+    Stop,
+}
+
+const DIRECTIVES: &[u8] = b"acdfgGhHlmMnpPstuUyY";
+const TIME_DIRECTIVES: &[u8] = b"@aAbBdDFhHIjklmMprSTwyYZ+";
+
+impl Format {
+    pub fn parse(format: &str) -> std::result::Result<Self, String> {
+        let bytes = format.as_bytes();
+        let mut segments = Vec::new();
+        let mut literal = Vec::new();
+        let mut i = 0;
+        while i < bytes.len() {
+            let byte = bytes[i];
+            i += 1;
+            match byte {
+                b'\\' => match bytes.get(i) {
+                    Some(b'c') => {
+                        segments.push(Segment::Literal(mem::take(&mut literal)));
+                        segments.push(Segment::Stop);
+                        break;
+                    }
+                    Some(b'0'..=b'7') => {
+                        let digits = bytes[i..]
+                            .iter()
+                            .take(3)
+                            .take_while(|b| (b'0'..=b'7').contains(b))
+                            .count();
+                        let value = u32::from_str_radix(&format[i..i + digits], 8).unwrap_or(0);
+                        literal.push(value as u8);
+                        i += digits;
+                    }
+                    Some(&escape) => {
+                        literal.extend_from_slice(match escape {
+                            b'a' => b"\x07",
+                            b'b' => b"\x08",
+                            b'f' => b"\x0c",
+                            b'n' => b"\n",
+                            b'r' => b"\r",
+                            b't' => b"\t",
+                            b'v' => b"\x0b",
+                            b'\\' => b"\\",
+                            _ => return Err(format!("invalid escape '\\{}'", escape as char)),
+                        });
+                        i += 1;
+                    }
+                    None => literal.push(b'\\'),
+                },
+                b'%' => {
+                    let left = bytes.get(i) == Some(&b'-');
+                    if left {
+                        i += 1;
+                    }
+                    let digits = bytes[i..].iter().take_while(|b| b.is_ascii_digit()).count();
+                    let width = format[i..i + digits].parse().unwrap_or(0);
+                    i += digits;
+
+                    let directive = match bytes.get(i) {
+                        Some(b'%') if !left && digits == 0 => {
+                            literal.push(b'%');
+                            i += 1;
+                            continue;
+                        }
+                        Some(&directive) if DIRECTIVES.contains(&directive) => directive,
+                        Some(b'A' | b'C' | b'T') => bytes[i],
+                        Some(_) => {
+                            let directive = format[i..].chars().next().unwrap_or('%');
+                            return Err(format!("invalid format directive '%{directive}'"));
+                        }
+                        None => return Err("format ends with an incomplete directive".to_string()),
+                    };
+                    i += 1;
+
+                    let time = if matches!(directive, b'A' | b'C' | b'T') {
+                        match bytes.get(i) {
+                            Some(&time) if TIME_DIRECTIVES.contains(&time) => {
+                                i += 1;
+                                Some(time)
+                            }
+                            _ => {
+                                let time = format[i..].chars().next().unwrap_or(' ');
+                                return Err(format!(
+                                    "invalid format directive '%{}{time}'",
+                                    directive as char
+                                ));
+                            }
+                        }
+                    } else {
+                        None
+                    };
+
+                    segments.push(Segment::Literal(mem::take(&mut literal)));
+                    segments.push(Segment::Field {
+                        directive,
+                        time,
+                        width,
+                        left,
+                    });
+                }
+                byte => literal.push(byte),
+            }
+        }
+
+        segments.push(Segment::Literal(literal));
+        segments.retain(|segment| !matches!(segment, Segment::Literal(l) if l.is_empty()));
+        Ok(Self(segments))
+    }
+
+    fn render(&self, candidate: &Candidate, buf: &mut Vec<u8>) {
+        for segment in &self.0 {
+            match segment {
+                Segment::Literal(literal) => buf.extend_from_slice(literal),
+                Segment::Field {
+                    directive,
+                    time,
+                    width,
+                    left,
+                } => {
+                    let value = field(candidate, *directive, *time);
+                    let padding =
+                        width.saturating_sub(String::from_utf8_lossy(&value).chars().count());
+                    if !left {
+                        buf.resize(buf.len() + padding, b' ');
+                    }
+                    buf.extend_from_slice(&value);
+                    if *left {
+                        buf.resize(buf.len() + padding, b' ');
+                    }
+                }
+                Segment::Stop => return,
+            }
+        }
+    }
+}
+
+/// The value of a `-printf` directive, empty when the metadata it needs
+/// cannot be read
+fn field(candidate: &Candidate, directive: u8, time: Option<u8>) -> Vec<u8> {
+    let entry = candidate.entry;
+    let path = entry.path();
+    let bytes = |path: &Path| path.as_os_str().as_encoded_bytes().to_vec();
+    // The starting point the entry was found under
+    let root = path.ancestors().nth(entry.depth()).unwrap_or(path);
+    match directive {
+        b'p' => return bytes(path),
+        b'f' => return entry.file_name().as_encoded_bytes().to_vec(),
+        b'h' => {
+            return match path.parent() {
+                Some(parent) if !parent.as_os_str().is_empty() => bytes(parent),
+                _ => b".".to_vec(),
+            }
+        }
+        b'H' => return bytes(root),
+        b'P' => return path.strip_prefix(root).map(bytes).unwrap_or_default(),
+        b'd' => return entry.depth().to_string().into_bytes(),
+        b'y' => return type_letter(candidate.file_type()).to_string().into_bytes(),
+        b'Y' => {
+            let letter = fs::metadata(path).map_or('N', |m| type_letter(m.file_type()));
+            return letter.to_string().into_bytes();
+        }
+        b'l' if entry.path_is_symlink() => {
+            return fs::read_link(path).map(|t| bytes(&t)).unwrap_or_default()
+        }
+        b'l' => return Vec::new(),
+        _ => {}
+    }
+
+    let Some(metadata) = candidate.metadata() else {
+        return Vec::new();
+    };
+    let timestamp = |letter| match letter {
+        b'a' | b'A' => Timestamp::Accessed,
+        b'c' | b'C' => Timestamp::Changed,
+        _ => Timestamp::Modified,
+    };
+    let value = match directive {
+        b's' => metadata.len().to_string(),
+        b'm' => format!("{:o}", sys::mode(metadata) & 0o7777),
+        b'M' => symbolic_mode(type_letter(metadata.file_type()), sys::mode(metadata)),
+        b'n' => sys::nlink(metadata).to_string(),
+        b'U' => sys::uid(metadata).to_string(),
+        b'G' => sys::gid(metadata).to_string(),
+        b'u' => {
+            let uid = sys::uid(metadata);
+            sys::lookup_name("/etc/passwd", uid).unwrap_or_else(|| uid.to_string())
+        }
+        b'g' => {
+            let gid = sys::gid(metadata);
+            sys::lookup_name("/etc/group", gid).unwrap_or_else(|| gid.to_string())
+        }
+        b'a' | b'c' | b't' => match timestamp(directive).get(metadata) {
+            Some(time) => format_time(time, b'c'),
+            None => String::new(),
+        },
+        _ => match (timestamp(directive).get(metadata), time) {
+            (Some(time), Some(conversion)) => format_time(time, conversion),
+            _ => String::new(),
+        },
+    };
+
+    value.into_bytes()
+}
+
+/// The letter of `-type` for `file_type`, `f` for regular files
+fn type_letter(file_type: fs::FileType) -> char {
+    if file_type.is_symlink() {
+        'l'
+    } else if file_type.is_dir() {
+        'd'
+    } else if file_type.is_file() {
+        'f'
+    } else {
+        sys::special_type(file_type).unwrap_or('U')
+    }
+}
+
+/// The mode as `ls -l` shows it, such as `-rwxr-xr-x`
+fn symbolic_mode(type_letter: char, mode: u32) -> String {
+    let mut symbolic = String::from(if type_letter == 'f' { '-' } else { type_letter });
+    for (shift, special, letter) in [(6, 0o4000, 's'), (3, 0o2000, 's'), (0, 0o1000, 't')] {
+        let bits = mode >> shift;
+        symbolic.push(if bits & 4 != 0 { 'r' } else { '-' });
+        symbolic.push(if bits & 2 != 0 { 'w' } else { '-' });
+        symbolic.push(match (mode & special != 0, bits & 1 != 0) {
+            (true, true) => letter,
+            (true, false) => letter.to_ascii_uppercase(),
+            (false, true) => 'x',
+            (false, false) => '-',
+        });
+    }
+
+    symbolic
+}
+
+const WEEKDAYS: [&str; 7] = [
+    "Sunday",
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+];
+const MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+/// Days since 1970-01-01 of a date of the proleptic Gregorian calendar
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+/// Formats `time` in UTC for the conversion letter of `%T`, or `c` for
+/// the format of `%t`
+fn format_time(time: SystemTime, conversion: u8) -> String {
+    let (seconds, nanos) = match time.duration_since(SystemTime::UNIX_EPOCH) {
+        Ok(since) => (since.as_secs() as i64, since.subsec_nanos()),
+        Err(e) if e.duration().subsec_nanos() == 0 => (-(e.duration().as_secs() as i64), 0),
+        Err(e) => (
+            -(e.duration().as_secs() as i64) - 1,
+            1_000_000_000 - e.duration().subsec_nanos(),
+        ),
+    };
+
+    let days = seconds.div_euclid(86400);
+    let (hour, minute, second) = {
+        let rem = seconds.rem_euclid(86400);
+        (rem / 3600, rem / 60 % 60, rem % 60)
+    };
+
+    // The inverse of days_from_civil
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    let weekday = (days + 4).rem_euclid(7) as usize;
+    let month_name = MONTHS[month as usize - 1];
+    let hour12 = if hour % 12 == 0 { 12 } else { hour % 12 };
+    let am_pm = if hour < 12 { "AM" } else { "PM" };
+    let fraction = format!("{nanos:09}0");
+
+    match conversion {
+        b'@' => format!("{seconds}.{fraction}"),
+        b'H' => format!("{hour:02}"),
+        b'I' => format!("{hour12:02}"),
+        b'k' => format!("{hour:2}"),
+        b'l' => format!("{hour12:2}"),
+        b'M' => format!("{minute:02}"),
+        b'p' => am_pm.to_string(),
+        b'r' => format!("{hour12:02}:{minute:02}:{second:02} {am_pm}"),
+        b'S' => format!("{second:02}.{fraction}"),
+        b'T' => format!("{hour:02}:{minute:02}:{second:02}.{fraction}"),
+        b'Z' => "UTC".to_string(),
+        b'+' => {
+            format!("{year:04}-{month:02}-{day:02}+{hour:02}:{minute:02}:{second:02}.{fraction}")
+        }
+        b'a' => WEEKDAYS[weekday][..3].to_string(),
+        b'A' => WEEKDAYS[weekday].to_string(),
+        b'b' | b'h' => month_name[..3].to_string(),
+        b'B' => month_name.to_string(),
+        b'd' => format!("{day:02}"),
+        b'D' => format!("{month:02}/{day:02}/{:02}", year.rem_euclid(100)),
+        b'F' => format!("{year:04}-{month:02}-{day:02}"),
+        b'j' => format!("{:03}", days - days_from_civil(year, 1, 1) + 1),
+        b'm' => format!("{month:02}"),
+        b'w' => weekday.to_string(),
+        b'y' => format!("{:02}", year.rem_euclid(100)),
+        b'Y' => year.to_string(),
+        _ => format!(
+            "{} {} {day:2} {hour:02}:{minute:02}:{second:02}.{fraction} {year}",
+            &WEEKDAYS[weekday][..3],
+            &month_name[..3],
+        ),
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+    use std::time::Duration;
+    use walkdir::WalkDir;
+
+    fn render(format: &str, path: &str) -> String {
+        let entry = WalkDir::new(path).into_iter().next().unwrap().unwrap();
+        let mut buf = Vec::new();
+        Format::parse(format)
+            .unwrap()
+            .render(&Candidate::new(&entry, false), &mut buf);
+        String::from_utf8(buf).unwrap()
+    }
+
+    #[test]
+    fn test_format() {
+        let path = "tests/find/b/a/a.txt";
+        assert_eq!(
+            render(r"%p %f %h %d %y %s\n", path),
+            format!("{path} a.txt tests/find/b/a 0 f 0\n")
+        );
+        assert_eq!(
+            render("[%-7f|%7P] %H", path),
+            format!("[a.txt  |       ] {path}")
+        );
+        assert_eq!(
+            render(r"%y%%\t\101\0\\\cignored", "tests/find/b"),
+            "d%\tA\0\\"
+        );
+
+        assert_eq!(
+            Format::parse("%q").unwrap_err(),
+            "invalid format directive '%q'"
+        );
+        assert_eq!(
+            Format::parse("%Tq").unwrap_err(),
+            "invalid format directive '%Tq'"
+        );
+        assert_eq!(
+            Format::parse("%-").unwrap_err(),
+            "format ends with an incomplete directive"
+        );
+        assert_eq!(Format::parse(r"\q").unwrap_err(), r"invalid escape '\q'");
+    }
+
+    #[test]
+    fn test_format_time() {
+        let time = SystemTime::UNIX_EPOCH + Duration::new(1_709_211_909, 250_000_000);
+        let format = |conversion| format_time(time, conversion);
+        assert_eq!(format(b'c'), "Thu Feb 29 13:05:09.2500000000 2024");
+        assert_eq!(format(b'+'), "2024-02-29+13:05:09.2500000000");
+        assert_eq!(format(b'@'), "1709211909.2500000000");
+        assert_eq!(format(b'j'), "060");
+        assert_eq!(format(b'r'), "01:05:09 PM");
+        assert_eq!(format(b'D'), "02/29/24");
+
+        let before = SystemTime::UNIX_EPOCH - Duration::new(1, 500_000_000);
+        assert_eq!(format_time(before, b'+'), "1969-12-31+23:59:58.5000000000");
+        assert_eq!(format_time(before, b'A'), "Wednesday");
+    }
+
+    #[test]
+    fn test_helpers() {
+        assert_eq!(symbolic_mode('f', 0o644), "-rw-r--r--");
+        assert_eq!(symbolic_mode('d', 0o1777), "drwxrwxrwt");
+        assert_eq!(symbolic_mode('f', 0o6744), "-rwsr-Sr--");
+        assert_eq!(
+            substitute("{}:{}.bak", OsStr::new("a b")),
+            OsString::from("a b:a b.bak")
+        );
+        assert_eq!(substitute("echo", OsStr::new("x")), OsString::from("echo"));
+    }
+}
@@ -0,0 +1,853 @@
+//! The expression language of find: tests such as `-type d`, combined
+//! with `!`, `-a`, `-o` and parentheses, as in
+//! `\( -type f -o -type l \) ! '^\.'`. Tests on metadata, such as
+//! `-size` or `-mtime`, read it only once per entry and only when reached.
+//! Actions, such as `-print` or `-exec`, are true or false like tests
+
+use std::{
+    cell::OnceCell,
+    fs::{self, FileType, Metadata},
+    io::Write,
+    time::SystemTime,
+};
+
+use globset::{GlobBuilder, GlobMatcher};
+use regex::{Regex, RegexBuilder};
+use walkdir::DirEntry;
+
+use super::{
+    action::{Action, Context, Exec, Format},
+    sys, EntryType,
+};
+use crate::{Error, Result};
+
+/// A parsed expression. `And` and `Or` evaluate their right side only
+/// when the left one does not decide the result
+#[derive(Clone, Debug)]
+pub enum Expr {
+    /// Always true, the empty expression
+    True,
+    /// The file name matches the regex, a bare word in the expression
+    NameRegex(Regex),
+    /// `-name` and `-iname`: the file name matches the glob
+    Name(GlobMatcher),
+    /// `-path` and the like: the path matches the glob
+    Path(GlobMatcher),
+    /// `-regex` and `-iregex`: the whole path matches the regex
+    PathRegex(Regex),
+    /// The entry is of any of the types
+    Type(Vec<EntryType>),
+    /// The size, rounded up to units of the given number of bytes
+    Size(Cmp, u64),
+    /// The time since `now`, rounded down to units of the given number of
+    /// seconds
+    Age {
+        timestamp: Timestamp,
+        unit: u64,
+        cmp: Cmp,
+        now: SystemTime,
+    },
+    /// Modified after the time
+    Newer(SystemTime),
+    /// Permission bits, see `Perm`
+    Perm(Perm, u32),
+    Uid(Cmp),
+    Gid(Cmp),
+    /// An empty regular file or directory
+    Empty,
+    /// The number of hard links
+    Links(Cmp),
+    /// An option of the whole search, always true
+    Setting(Setting),
+    /// `-prune`: do not descend into the directory, always true
+    Prune,
+    Action(Action),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    /// Parses the words of an expression. From the highest precedence to
+    /// the lowest: parentheses, `!` or `-not`, `-a` or `-and`, which two
+    /// adjacent tests imply, and `-o` or `-or`
+    pub fn parse<S: AsRef<str>>(words: &[S]) -> Result<Self> {
+        let words: Vec<&str> = words.iter().map(AsRef::as_ref).collect();
+        if words.is_empty() {
+            return Ok(Self::True);
+        }
+
+        let mut parser = Parser {
+            words,
+            pos: 0,
+            now: SystemTime::now(),
+        };
+        let expr = parser.or()?;
+        match parser.peek() {
+            None => {}
+            Some(")") => return Err(invalid("unexpected ')'")),
+            Some(word) => return Err(invalid(format!("unexpected '{word}'"))),
+        }
+
+        // -delete turns on -depth, under which -prune cannot skip anything
+        // and what it was meant to keep would be deleted, as in GNU find
+        let delete = expr.any(&|expr| matches!(expr, Self::Action(Action::Delete)));
+        if delete
+            && expr.any(&|expr| matches!(expr, Self::Prune))
+            && !expr.any(&|expr| matches!(expr, Self::Setting(Setting::ContentsFirst)))
+        {
+            return Err(Error::usage(
+                "find",
+                "-delete turns on -depth, but -prune does nothing under -depth, \
+                 give -depth explicitly to carry on anyway",
+            ));
+        }
+
+        Ok(expr)
+    }
+
+    /// Whether `pred` holds for the expression or any part of it
+    fn any(&self, pred: &impl Fn(&Self) -> bool) -> bool {
+        pred(self)
+            || match self {
+                Self::Not(expr) => expr.any(pred),
+                Self::And(left, right) | Self::Or(left, right) => left.any(pred) || right.any(pred),
+                _ => false,
+            }
+    }
+
+    /// `self -a other`
+    pub fn and(self, other: Self) -> Self {
+        match (self, other) {
+            (Self::True, expr) | (expr, Self::True) => expr,
+            (left, right) => Self::And(Box::new(left), Box::new(right)),
+        }
+    }
+
+    /// Whether the expression has an action, otherwise find prints the
+    /// entries for which it is true
+    pub fn has_action(&self) -> bool {
+        match self {
+            Self::Action(_) => true,
+            Self::Not(expr) => expr.has_action(),
+            Self::And(left, right) | Self::Or(left, right) => {
+                left.has_action() || right.has_action()
+            }
+            _ => false,
+        }
+    }
+
+    /// How to walk the file tree, as set by the options in the expression
+    pub fn traversal(&self) -> Traversal {
+        let mut traversal = Traversal::default();
+        self.configure(&mut traversal);
+        traversal
+    }
+
+    fn configure(&self, traversal: &mut Traversal) {
+        match self {
+            Self::Setting(setting) => match *setting {
+                Setting::MinDepth(depth) => traversal.min_depth = Some(depth),
+                Setting::MaxDepth(depth) => traversal.max_depth = Some(depth),
+                Setting::SameFileSystem => traversal.same_file_system = true,
+                Setting::ContentsFirst => traversal.contents_first = true,
+            },
+            // Directories are only empty once their contents are deleted
+            Self::Action(Action::Delete) => traversal.contents_first = true,
+            Self::Not(expr) => expr.configure(traversal),
+            Self::And(left, right) | Self::Or(left, right) => {
+                left.configure(traversal);
+                right.configure(traversal);
+            }
+            _ => {}
+        }
+    }
+
+    /// Evaluates the expression on `entry`, running the actions reached
+    pub fn eval<W: Write>(&mut self, entry: &DirEntry, context: &mut Context<W>) -> Result<bool> {
+        let candidate = Candidate::new(entry, context.follow_root_links);
+        self.test(&candidate, context)
+    }
+
+    /// Runs the commands that `-exec ... +` still holds, at the end
+    pub fn finish<W: Write>(&mut self, context: &mut Context<W>) -> Result<()> {
+        match self {
+            Self::Action(Action::Exec(exec)) => exec.flush(context),
+            Self::Not(expr) => expr.finish(context),
+            Self::And(left, right) | Self::Or(left, right) => {
+                left.finish(context)?;
+                right.finish(context)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn test<W: Write>(&mut self, candidate: &Candidate, context: &mut Context<W>) -> Result<bool> {
+        let entry = candidate.entry;
+        Ok(match self {
+            Self::True => true,
+            Self::NameRegex(regex) => regex.is_match(&entry.file_name().to_string_lossy()),
+            Self::Name(glob) => glob.is_match(entry.file_name()),
+            Self::Path(glob) => glob.is_match(entry.path()),
+            Self::PathRegex(regex) => regex.is_match(&entry.path().to_string_lossy()),
+            Self::Type(types) => types.iter().any(|entry_type| match entry_type {
+                EntryType::File => candidate.file_type().is_file(),
+                EntryType::Directory => candidate.file_type().is_dir(),
+                EntryType::Symlink => candidate.file_type().is_symlink(),
+            }),
+            Self::Setting(_) => true,
+            Self::Prune => {
+                context.prune = true;
+                true
+            }
+            Self::Action(action) => action.run(candidate, context)?,
+            Self::Not(expr) => !expr.test(candidate, context)?,
+            Self::And(left, right) => {
+                left.test(candidate, context)? && right.test(candidate, context)?
+            }
+            Self::Or(left, right) => {
+                left.test(candidate, context)? || right.test(candidate, context)?
+            }
+            _ => candidate
+                .metadata()
+                .is_some_and(|metadata| self.test_metadata(entry, metadata)),
+        })
+    }
+
+    fn test_metadata(&self, entry: &DirEntry, metadata: &Metadata) -> bool {
+        match self {
+            Self::Size(cmp, unit) => cmp.test(metadata.len().div_ceil(*unit)),
+            Self::Age {
+                timestamp,
+                unit,
+                cmp,
+                now,
+            } => timestamp.get(metadata).is_some_and(|time| {
+                let seconds = match now.duration_since(time) {
+                    Ok(age) => age.as_secs() as i64,
+                    Err(e) => -(e.duration().as_secs() as i64),
+                };
+                cmp.test_signed(seconds.div_euclid(*unit as i64))
+            }),
+            Self::Newer(time) => metadata.modified().is_ok_and(|t| t > *time),
+            Self::Perm(perm, mode) => perm.test(sys::mode(metadata) & 0o7777, *mode),
+            Self::Uid(cmp) => cmp.test(sys::uid(metadata).into()),
+            Self::Gid(cmp) => cmp.test(sys::gid(metadata).into()),
+            Self::Empty if metadata.is_dir() => {
+                fs::read_dir(entry.path()).is_ok_and(|mut entries| entries.next().is_none())
+            }
+            Self::Empty => metadata.is_file() && metadata.len() == 0,
+            Self::Links(cmp) => cmp.test(sys::nlink(metadata)),
+            _ => unreachable!("not a metadata test"),
+        }
+    }
+}
+
+/// Options that apply to the whole search wherever they are in the
+/// expression
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Setting {
+    /// `-mindepth N`
+    MinDepth(usize),
+    /// `-maxdepth N`
+    MaxDepth(usize),
+    /// `-xdev` and `-mount`
+    SameFileSystem,
+    /// `-depth`: the contents of a directory before the directory
+    ContentsFirst,
+}
+
+/// How to walk the file tree
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Traversal {
+    pub min_depth: Option<usize>,
+    pub max_depth: Option<usize>,
+    pub same_file_system: bool,
+    pub contents_first: bool,
+}
+
+/// An entry under test, with its metadata read on first use
+pub(super) struct Candidate<'a> {
+    pub(super) entry: &'a DirEntry,
+    /// A path given to -H, where a symlink stands for its target
+    follow: bool,
+    metadata: OnceCell<Option<Metadata>>,
+}
+
+impl<'a> Candidate<'a> {
+    pub(super) fn new(entry: &'a DirEntry, follow_root_links: bool) -> Self {
+        Self {
+            entry,
+            follow: follow_root_links && entry.depth() == 0 && entry.path_is_symlink(),
+            metadata: OnceCell::new(),
+        }
+    }
+
+    pub(super) fn metadata(&self) -> Option<&Metadata> {
+        self.metadata
+            .get_or_init(|| {
+                if self.follow {
+                    fs::metadata(self.entry.path()).ok()
+                } else {
+                    self.entry.metadata().ok()
+                }
+            })
+            .as_ref()
+    }
+
+    /// The type of the entry, of the target of a followed symlink unless
+    /// it is broken
+    pub(super) fn file_type(&self) -> FileType {
+        match self.metadata() {
+            Some(metadata) if self.follow => metadata.file_type(),
+            _ => self.entry.file_type(),
+        }
+    }
+}
+
+/// A numeric argument: `+N` for more than N, `-N` for less than N and `N`
+/// for exactly N
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Cmp {
+    Less(u64),
+    Equal(u64),
+    Greater(u64),
+}
+
+impl Cmp {
+    fn parse(arg: &str) -> Option<(Self, &str)> {
+        let (cmp, digits): (fn(u64) -> Self, _) = match arg.as_bytes().first()? {
+            b'+' => (Self::Greater, &arg[1..]),
+            b'-' => (Self::Less, &arg[1..]),
+            _ => (Self::Equal, arg),
+        };
+        let end = digits
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(digits.len());
+        let n = digits[..end].parse().ok()?;
+        Some((cmp(n), &digits[end..]))
+    }
+
+    fn test(&self, n: u64) -> bool {
+        match *self {
+            Self::Less(m) => n < m,
+            Self::Equal(m) => n == m,
+            Self::Greater(m) => n > m,
+        }
+    }
+
+    /// As `test`, for ages of files from the future
+    fn test_signed(&self, n: i64) -> bool {
+        u64::try_from(n).map_or(matches!(self, Self::Less(_)), |n| self.test(n))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Timestamp {
+    Accessed,
+    Changed,
+    Modified,
+}
+
+impl Timestamp {
+    pub(super) fn get(&self, metadata: &Metadata) -> Option<SystemTime> {
+        match self {
+            Self::Accessed => metadata.accessed().ok(),
+            Self::Changed => sys::changed(metadata),
+            Self::Modified => metadata.modified().ok(),
+        }
+    }
+}
+
+/// How `-perm` compares the permission bits with its mode
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Perm {
+    /// `-perm MODE`: exactly the mode
+    Exact,
+    /// `-perm -MODE`: all the bits of the mode
+    All,
+    /// `-perm /MODE`: any of the bits of the mode
+    Any,
+}
+
+impl Perm {
+    fn test(&self, bits: u32, mode: u32) -> bool {
+        match self {
+            Self::Exact => bits == mode,
+            Self::All => bits & mode == mode,
+            Self::Any => mode == 0 || bits & mode != 0,
+        }
+    }
+}
+
+/// Parses an octal mode, or a symbolic one such as `u+rw,g=r` applied to
+/// no permissions
+fn parse_mode(mode: &str) -> Option<u32> {
+    if mode.bytes().all(|b| b.is_ascii_digit()) {
+        return u32::from_str_radix(mode, 8).ok().filter(|&m| m <= 0o7777);
+    }
+
+    let mut bits = 0;
+    for clause in mode.split(',') {
+        let op = clause.find(['+', '-', '='])?;
+        let mut who = 0;
+        for c in clause[..op].chars() {
+            who |= match c {
+                'u' => 0o4700,
+                'g' => 0o2070,
+                'o' => 0o0007,
+                'a' => 0o6777,
+                _ => return None,
+            };
+        }
+        if who == 0 {
+            who = 0o6777;
+        }
+
+        let mut perms = 0;
+        for c in clause[op + 1..].chars() {
+            perms |= match c {
+                'r' => 0o444,
+                'w' => 0o222,
+                'x' | 'X' => 0o111,
+                's' => 0o6000,
+                't' => 0o1000,
+                _ => return None,
+            };
+        }
+        // The sticky bit belongs to no class in particular
+        let perms = perms & (who | 0o1000);
+
+        match &clause[op..=op] {
+            "+" => bits |= perms,
+            "-" => bits &= !perms,
+            _ => bits = bits & !who | perms,
+        }
+    }
+
+    Some(bits)
+}
+
+fn invalid(message: impl AsRef<str>) -> Error {
+    Error::usage("find", format!("invalid expression: {}", message.as_ref()))
+}
+
+/// Recursive descent over the words, one method per precedence level
+struct Parser<'a> {
+    words: Vec<&'a str>,
+    pos: usize,
+    /// The reference of `-mtime` and the like, the start of the search
+    now: SystemTime,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&'a str> {
+        self.words.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<&'a str> {
+        let word = self.peek();
+        self.pos += 1;
+        word
+    }
+
+    fn or(&mut self) -> Result<Expr> {
+        let mut expr = self.and()?;
+        while let Some("-o" | "-or") = self.peek() {
+            self.pos += 1;
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr> {
+        let mut expr = self.not()?;
+        loop {
+            match self.peek() {
+                Some("-a" | "-and") => self.pos += 1,
+                // Two tests in a row are joined by an implicit -a
+                Some(word) if word != ")" && word != "-o" && word != "-or" => {}
+                _ => return Ok(expr),
+            }
+
+            expr = Expr::And(Box::new(expr), Box::new(self.not()?));
+        }
+    }
+
+    fn not(&mut self) -> Result<Expr> {
+        match self.peek() {
+            Some("!" | "-not") => {
+                self.pos += 1;
+                Ok(Expr::Not(Box::new(self.not()?)))
+            }
+            _ => self.primary(),
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expr> {
+        let Some(word) = self.next() else {
+            let last = self.words[self.words.len() - 1];
+            return Err(invalid(format!("expected an expression after '{last}'")));
+        };
+
+        match word {
+            "(" => {
+                if self.peek() == Some(")") {
+                    return Err(invalid("empty parentheses"));
+                }
+
+                let expr = self.or()?;
+                match self.next() {
+                    Some(")") => Ok(expr),
+                    _ => Err(invalid("missing ')'")),
+                }
+            }
+            ")" | "-a" | "-and" | "-o" | "-or" => {
+                Err(invalid(format!("expected an expression before '{word}'")))
+            }
+            "-type" => {
+                let types = self.argument(word)?;
+                types
+                    .split(',')
+                    .map(|t| t.parse().map_err(|e: String| Error::usage("find", e)))
+                    .collect::<Result<_>>()
+                    .map(Expr::Type)
+            }
+            "-size" => {
+                let arg = self.argument(word)?;
+                let (cmp, unit) = Cmp::parse(arg).ok_or_else(|| invalid_argument(arg, word))?;
+                let unit = match unit {
+                    "" | "b" => 512,
+                    "c" => 1,
+                    "w" => 2,
+                    "k" => 1 << 10,
+                    "M" => 1 << 20,
+                    "G" => 1 << 30,
+                    _ => return Err(invalid_argument(arg, word)),
+                };
+                Ok(Expr::Size(cmp, unit))
+            }
+            "-amin" | "-atime" | "-cmin" | "-ctime" | "-mmin" | "-mtime" => {
+                let timestamp = match &word[1..2] {
+                    "a" => Timestamp::Accessed,
+                    "c" => Timestamp::Changed,
+                    _ => Timestamp::Modified,
+                };
+                let unit = if word.ends_with("min") {
+                    60
+                } else {
+                    24 * 60 * 60
+                };
+                Ok(Expr::Age {
+                    timestamp,
+                    unit,
+                    cmp: self.number(word)?,
+                    now: self.now,
+                })
+            }
+            "-newer" => {
+                let path = self.argument(word)?;
+                fs::metadata(path)
+                    .and_then(|metadata| metadata.modified())
+                    .map(Expr::Newer)
+                    .map_err(|e| Error::io("find", path, e))
+            }
+            "-perm" => {
+                let arg = self.argument(word)?;
+                let (perm, mode) = match arg.as_bytes().first() {
+                    Some(b'-') => (Perm::All, &arg[1..]),
+                    Some(b'/') => (Perm::Any, &arg[1..]),
+                    _ => (Perm::Exact, arg),
+                };
+                parse_mode(mode)
+                    .map(|mode| Expr::Perm(perm, mode))
+                    .ok_or_else(|| Error::usage("find", format!("invalid mode '{arg}'")))
+            }
+            "-user" | "-group" => {
+                let name = self.argument(word)?;
+                let (database, kind) = match word {
+                    "-user" => ("/etc/passwd", "user"),
+                    _ => ("/etc/group", "group"),
+                };
+                let id = sys::lookup_id(database, name).ok_or_else(|| {
+                    Error::usage(
+                        "find",
+                        format!("'{name}' is not the name of a known {kind}"),
+                    )
+                })?;
+                let cmp = Cmp::Equal(id.into());
+                Ok(if kind == "user" {
+                    Expr::Uid(cmp)
+                } else {
+                    Expr::Gid(cmp)
+                })
+            }
+            "-uid" => self.number(word).map(Expr::Uid),
+            "-gid" => self.number(word).map(Expr::Gid),
+            "-links" => self.number(word).map(Expr::Links),
+            "-empty" => Ok(Expr::Empty),
+            "-mindepth" | "-maxdepth" => {
+                let arg = self.argument(word)?;
+                let depth = arg.parse().map_err(|_| invalid_argument(arg, word))?;
+                Ok(Expr::Setting(if word == "-mindepth" {
+                    Setting::MinDepth(depth)
+                } else {
+                    Setting::MaxDepth(depth)
+                }))
+            }
+            "-xdev" | "-mount" => Ok(Expr::Setting(Setting::SameFileSystem)),
+            "-depth" => Ok(Expr::Setting(Setting::ContentsFirst)),
+            "-prune" => Ok(Expr::Prune),
+            "-name" | "-iname" | "-path" | "-ipath" | "-wholename" | "-iwholename" => {
+                let pattern = self.argument(word)?;
+                let glob = GlobBuilder::new(pattern)
+                    .case_insensitive(word.starts_with("-i"))
+                    .literal_separator(false)
+                    .backslash_escape(true)
+                    .build()
+                    .map_err(|e| Error::usage("find", e.to_string()))?
+                    .compile_matcher();
+                Ok(if word.ends_with("name") && !word.ends_with("wholename") {
+                    Expr::Name(glob)
+                } else {
+                    Expr::Path(glob)
+                })
+            }
+            "-regex" | "-iregex" => {
+                let pattern = self.argument(word)?;
+                RegexBuilder::new(&format!("^(?:{pattern})$"))
+                    .case_insensitive(word == "-iregex")
+                    .build()
+                    .map(Expr::PathRegex)
+                    .map_err(|e| Error::usage("find", e.to_string()))
+            }
+            "-regextype" => match self.argument(word)? {
+                // The regex crate reads extended regular expressions alike
+                "rust" | "posix-extended" | "posix-egrep" | "egrep" => Ok(Expr::True),
+                regex_type => Err(Error::usage(
+                    "find",
+                    format!("unsupported regular expression type '{regex_type}'"),
+                )),
+            },
+            "-print" => Ok(Expr::Action(Action::Print(b'\n'))),
+            "-print0" => Ok(Expr::Action(Action::Print(b'\0'))),
+            "-printf" => {
+                let format = self.argument(word)?;
+                Format::parse(format)
+                    .map(|format| Expr::Action(Action::Printf(format)))
+                    .map_err(|e| Error::usage("find", e))
+            }
+            "-exec" | "-execdir" | "-ok" | "-okdir" => {
+                let start = self.pos;
+                let confirm = word.starts_with("-ok");
+                let (end, batched) = loop {
+                    match self.next() {
+                        Some(";") => break (self.pos - 1, false),
+                        // {} + ends -exec only right after {}
+                        Some("+")
+                            if !confirm
+                                && self.pos - 1 > start
+                                && self.words[self.pos - 2] == "{}" =>
+                        {
+                            break (self.pos - 2, true)
+                        }
+                        Some(_) => {}
+                        None => return Err(missing_argument(word)),
+                    }
+                };
+
+                let command = &self.words[start..end];
+                if command.is_empty() {
+                    return Err(missing_argument(word));
+                }
+                if batched && command.iter().any(|arg| arg.contains("{}")) {
+                    return Err(Error::usage(
+                        "find",
+                        format!("only one instance of {{}} is supported with {word} ... +"),
+                    ));
+                }
+
+                let exec = Exec::new(command, word.ends_with("dir"), confirm, batched);
+                Ok(Expr::Action(Action::Exec(exec)))
+            }
+            "-delete" => Ok(Expr::Action(Action::Delete)),
+            word if word.starts_with('-') && word.len() > 1 => {
+                Err(invalid(format!("unknown test '{word}'")))
+            }
+            word => Regex::new(word)
+                .map(Expr::NameRegex)
+                .map_err(|e| Error::usage("find", e.to_string())),
+        }
+    }
+
+    /// The argument of the test `test`
+    fn argument(&mut self, test: &str) -> Result<&'a str> {
+        self.next().ok_or_else(|| missing_argument(test))
+    }
+
+    /// The `[+-]N` argument of the test `test`
+    fn number(&mut self, test: &str) -> Result<Cmp> {
+        let arg = self.argument(test)?;
+        match Cmp::parse(arg) {
+            Some((cmp, "")) => Ok(cmp),
+            _ => Err(invalid_argument(arg, test)),
+        }
+    }
+}
+
+fn missing_argument(test: &str) -> Error {
+    Error::usage("find", format!("missing argument to '{test}'"))
+}
+
+fn invalid_argument(arg: &str, test: &str) -> Error {
+    Error::usage("find", format!("invalid argument '{arg}' to '{test}'"))
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+    use walkdir::WalkDir;
+
+    fn entry(path: &str) -> DirEntry {
+        WalkDir::new(path).into_iter().next().unwrap().unwrap()
+    }
+
+    fn eval(words: &[&str], path: &str) -> bool {
+        let mut context = Context::new(Vec::new());
+        let matched = Expr::parse(words).unwrap().eval(&entry(path), &mut context);
+        matched.unwrap()
+    }
+
+    #[test]
+    fn test_parse() {
+        assert!(matches!(Expr::parse::<&str>(&[]), Ok(Expr::True)));
+        assert!(matches!(Expr::parse(&["a"]), Ok(Expr::NameRegex(_))));
+        assert!(matches!(
+            Expr::parse(&["a", "-o", "b", "c"]),
+            Ok(Expr::Or(_, right)) if matches!(*right, Expr::And(..))
+        ));
+        assert!(matches!(
+            Expr::parse(&["!", "a", "b"]),
+            Ok(Expr::And(left, _)) if matches!(*left, Expr::Not(_))
+        ));
+
+        let traversal = |words: &[&str]| Expr::parse(words).unwrap().traversal();
+        assert_eq!(traversal(&["a"]), Traversal::default());
+        assert_eq!(
+            traversal(&["-mindepth", "1", "a", "-o", "!", "-maxdepth", "2", "-xdev"]),
+            Traversal {
+                min_depth: Some(1),
+                max_depth: Some(2),
+                same_file_system: true,
+                contents_first: false,
+            }
+        );
+        assert!(traversal(&["a", "-o", "(", "-delete", ")"]).contents_first);
+        assert!(traversal(&["-depth"]).contents_first);
+        assert!(traversal(&["-depth", "-prune", "-o", "-delete"]).contents_first);
+
+        let error = |words: &[&str]| Expr::parse(words).unwrap_err().to_string();
+        assert_eq!(
+            error(&["-maxdepth", "-1"]),
+            "find: invalid argument '-1' to '-maxdepth'"
+        );
+        assert_eq!(error(&["(", "a"]), "find: invalid expression: missing ')'");
+        assert_eq!(
+            error(&["a", ")"]),
+            "find: invalid expression: unexpected ')'"
+        );
+        assert_eq!(
+            error(&["(", ")"]),
+            "find: invalid expression: empty parentheses"
+        );
+        assert_eq!(
+            error(&["-o", "a"]),
+            "find: invalid expression: expected an expression before '-o'"
+        );
+        assert_eq!(
+            error(&["a", "-a"]),
+            "find: invalid expression: expected an expression after '-a'"
+        );
+        assert_eq!(error(&["-type"]), "find: missing argument to '-type'");
+        assert_eq!(error(&["-type", "x"]), "find: Invalid entry type: x");
+        assert_eq!(
+            error(&["-bogus"]),
+            "find: invalid expression: unknown test '-bogus'"
+        );
+    }
+
+    #[test]
+    fn test_parse_metadata() {
+        assert_eq!(Cmp::parse("+3k"), Some((Cmp::Greater(3), "k")));
+        assert_eq!(Cmp::parse("-0"), Some((Cmp::Less(0), "")));
+        assert_eq!(Cmp::parse("12"), Some((Cmp::Equal(12), "")));
+        assert_eq!(Cmp::parse("+"), None);
+        assert!(Cmp::Less(1).test_signed(-2));
+        assert!(!Cmp::Greater(1).test_signed(-2));
+
+        assert_eq!(parse_mode("0754"), Some(0o754));
+        assert_eq!(parse_mode("u+rwx,g=rx,o+r"), Some(0o754));
+        assert_eq!(parse_mode("+x"), Some(0o111));
+        assert_eq!(parse_mode("a=rw,o-w"), Some(0o664));
+        assert_eq!(parse_mode("u+s,o+t"), Some(0o5000));
+        assert_eq!(parse_mode("10000"), None);
+        assert_eq!(parse_mode("u+q"), None);
+        assert!(Perm::All.test(0o755, 0o111));
+        assert!(!Perm::All.test(0o754, 0o111));
+        assert!(Perm::Any.test(0o754, 0o111));
+        assert!(Perm::Any.test(0o600, 0));
+
+        assert!(matches!(
+            Expr::parse(&["-size", "+2k"]),
+            Ok(Expr::Size(Cmp::Greater(2), 1024))
+        ));
+        assert!(matches!(
+            Expr::parse(&["-cmin", "5"]),
+            Ok(Expr::Age {
+                timestamp: Timestamp::Changed,
+                unit: 60,
+                cmp: Cmp::Equal(5),
+                ..
+            })
+        ));
+        assert!(matches!(
+            Expr::parse(&["-user", "0"]),
+            Ok(Expr::Uid(Cmp::Equal(0)))
+        ));
+
+        let error = |words: &[&str]| Expr::parse(words).unwrap_err().to_string();
+        assert_eq!(
+            error(&["-size", "1x"]),
+            "find: invalid argument '1x' to '-size'"
+        );
+        assert_eq!(
+            error(&["-mtime", "1d"]),
+            "find: invalid argument '1d' to '-mtime'"
+        );
+        assert_eq!(error(&["-perm", "/8"]), "find: invalid mode '/8'");
+        assert_eq!(
+            error(&["-group", "no such group"]),
+            "find: 'no such group' is not the name of a known group"
+        );
+    }
+
+    #[test]
+    fn test_eval() {
+        let file = "tests/find/a.txt";
+        let dir = "tests/find/b";
+        assert!(eval(&["a"], file));
+        assert!(!eval(&["a"], dir));
+        assert!(eval(&["-type", "d,l"], dir));
+        assert!(!eval(&["-type", "f", "b"], dir));
+        assert!(eval(&["-type", "f", "-o", "b"], dir));
+        assert!(eval(&["!", "-type", "f", "-a", "b"], dir));
+        assert!(eval(&["(", "a", "-o", "b", ")", "!", "-type", "f"], dir));
+        assert!(!eval(&["-not", "(", "a", "-or", "b", ")"], file));
+
+        assert!(eval(&["-size", "-1", "-empty"], file));
+        assert!(eval(&["-mmin", "-1000000000", "-links", "+0"], file));
+        assert!(!eval(&["-empty"], dir));
+        assert!(eval(&["-newer", file, "-o", "!", "-newer", file], dir));
+    }
+}
@@ -0,0 +1,121 @@
+//! Metadata that only unix has, with stand-ins elsewhere, and the user
+//! and group databases
+
+use std::fs;
+
+#[cfg(unix)]
+pub use self::unix::*;
+
+#[cfg(not(unix))]
+pub use self::other::*;
+
+/// The id of `name` in a file of the format of /etc/passwd or /etc/group,
+/// or `name` itself if it is numeric
+pub fn lookup_id(database: &str, name: &str) -> Option<u32> {
+    let by_name = fs::read_to_string(database).ok().and_then(|content| {
+        content.lines().find_map(|line| {
+            let mut fields = line.split(':');
+            if fields.next()? != name {
+                return None;
+            }
+            fields.nth(1)?.parse().ok()
+        })
+    });
+
+    by_name.or_else(|| name.parse().ok())
+}
+
+/// The name of `id` in a file of the format of /etc/passwd or /etc/group
+pub fn lookup_name(database: &str, id: u32) -> Option<String> {
+    let content = fs::read_to_string(database).ok()?;
+    content.lines().find_map(|line| {
+        let mut fields = line.split(':');
+        let name = fields.next()?;
+        (fields.nth(1)?.parse() == Ok(id)).then(|| name.to_string())
+    })
+}
+
+#[cfg(unix)]
+mod unix {
+    use std::{
+        fs::{FileType, Metadata},
+        os::unix::fs::{FileTypeExt, MetadataExt},
+        time::{Duration, SystemTime},
+    };
+
+    pub fn mode(metadata: &Metadata) -> u32 {
+        metadata.mode()
+    }
+
+    pub fn uid(metadata: &Metadata) -> u32 {
+        metadata.uid()
+    }
+
+    pub fn gid(metadata: &Metadata) -> u32 {
+        metadata.gid()
+    }
+
+    pub fn nlink(metadata: &Metadata) -> u64 {
+        metadata.nlink()
+    }
+
+    pub fn changed(metadata: &Metadata) -> Option<SystemTime> {
+        let ctime = Duration::new(
+            u64::try_from(metadata.ctime()).ok()?,
+            metadata.ctime_nsec() as u32,
+        );
+        SystemTime::UNIX_EPOCH.checked_add(ctime)
+    }
+
+    /// The letter of a type other than file, directory and symlink, as
+    /// used by `ls -l`
+    pub fn special_type(file_type: FileType) -> Option<char> {
+        if file_type.is_fifo() {
+            Some('p')
+        } else if file_type.is_socket() {
+            Some('s')
+        } else if file_type.is_char_device() {
+            Some('c')
+        } else if file_type.is_block_device() {
+            Some('b')
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(not(unix))]
+mod other {
+    use std::{
+        fs::{FileType, Metadata},
+        time::SystemTime,
+    };
+
+    pub fn mode(metadata: &Metadata) -> u32 {
+        if metadata.permissions().readonly() {
+            0o444
+        } else {
+            0o666
+        }
+    }
+
+    pub fn uid(_: &Metadata) -> u32 {
+        0
+    }
+
+    pub fn gid(_: &Metadata) -> u32 {
+        0
+    }
+
+    pub fn nlink(_: &Metadata) -> u64 {
+        1
+    }
+
+    pub fn changed(metadata: &Metadata) -> Option<SystemTime> {
+        metadata.modified().ok()
+    }
+
+    pub fn special_type(_: FileType) -> Option<char> {
+        None
+    }
+}
@@ -0,0 +1,2273 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    env,
+    ffi::OsString,
+    fs,
+    io::{self, BufRead, Write},
+    iter, mem,
+    num::NonZeroUsize,
+    ops::{AddAssign, Range},
+    path::Path,
+    process,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{channel, sync_channel},
+        Arc, Mutex,
+    },
+    thread,
+};
+
+use crate::{
+    chomp, open_file, open_input, stdout_is_terminal, Decompress, Error, Input, LineReader, Output,
+    Result, Terminator,
+};
+use aho_corasick::{AhoCorasick, MatchKind};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use clap::{ArgAction, Parser, ValueEnum};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use ignore::WalkBuilder;
+use memchr::{memchr, memrchr};
+use regex::bytes::{Regex, RegexBuilder};
+use serde::Serialize;
+
+#[derive(Parser, Debug)]
+#[command(
+    name = "grep",
+    author = "protium",
+    version = "0.1.0",
+    about = "grep searches for a pattern in the input files, selecting lines that match that pattern.",
+    disable_help_flag = true
+)]
+pub struct Args {
+    #[arg(
+        name = "pattern",
+        value_name = "PATTERN",
+        help = "Search pattern, unless -e or -f is given",
+        required_unless_present_any = ["regexp", "file"]
+    )]
+    pub pattern: Option<String>,
+
+    #[arg(
+        name = "files",
+        value_name = "FILES",
+        help = "Input file(s), standard input by default"
+    )]
+    pub files: Vec<String>,
+
+    #[arg(
+        name = "regexp",
+        value_name = "PATTERN",
+        short = 'e',
+        long = "regexp",
+        help = "Search for PATTERN, can be repeated"
+    )]
+    pub regexp: Vec<String>,
+
+    #[arg(
+        name = "file",
+        value_name = "FILE",
+        short = 'f',
+        long = "file",
+        help = "Search for the patterns in FILE, one per line"
+    )]
+    pub file: Vec<String>,
+
+    #[arg(
+        name = "word-regexp",
+        short = 'w',
+        long = "word-regexp",
+        help = "Match only whole words"
+    )]
+    pub word_regexp: bool,
+
+    #[arg(
+        name = "line-regexp",
+        short = 'x',
+        long = "line-regexp",
+        help = "Match only whole lines"
+    )]
+    pub line_regexp: bool,
+
+    #[arg(
+        name = "multiline",
+        short = 'U',
+        long = "multiline",
+        help = "Let matches span lines, and select every line a match touches. With (?s), . matches newlines too"
+    )]
+    pub multiline: bool,
+
+    #[arg(
+        name = "only-matching",
+        short = 'o',
+        long = "only-matching",
+        help = "Print only the matched parts of selected lines, one per line"
+    )]
+    pub only_matching: bool,
+
+    #[arg(
+        name = "fixed-strings",
+        short = 'F',
+        long = "fixed-strings",
+        help = "Patterns are fixed strings, not regular expressions"
+    )]
+    pub fixed_strings: bool,
+
+    #[arg(
+        name = "count",
+        help = "Count occurrences",
+        short = 'c',
+        long = "count"
+    )]
+    pub count: bool,
+
+    #[arg(
+        name = "ignore-case",
+        short = 'i',
+        long = "ignore-case",
+        help = "Ignore case distinctions"
+    )]
+    pub ignore_case: bool,
+
+    #[arg(
+        name = "invert-match",
+        help = "Inverse match",
+        short = 'v',
+        long = "invert-match"
+    )]
+    pub invert_match: bool,
+
+    #[arg(
+        name = "recursive",
+        help = "Recursive search",
+        short = 'r',
+        long = "recursive"
+    )]
+    pub recursive: bool,
+
+    #[arg(
+        name = "no-ignore",
+        long = "no-ignore",
+        help = "With -r, also search files listed in .gitignore and .ignore files, and .git directories"
+    )]
+    pub no_ignore: bool,
+
+    #[arg(
+        name = "include",
+        value_name = "GLOB",
+        long = "include",
+        help = "Search only files whose name matches GLOB, can be repeated"
+    )]
+    pub include: Vec<String>,
+
+    #[arg(
+        name = "exclude",
+        value_name = "GLOB",
+        long = "exclude",
+        help = "Skip files whose name matches GLOB, can be repeated"
+    )]
+    pub exclude: Vec<String>,
+
+    #[arg(
+        name = "exclude-dir",
+        value_name = "GLOB",
+        long = "exclude-dir",
+        help = "With -r, skip directories whose name matches GLOB, can be repeated"
+    )]
+    pub exclude_dir: Vec<String>,
+
+    #[arg(
+        name = "threads",
+        value_name = "NUM",
+        long = "threads",
+        help = "Number of files searched at once with -r, the number of CPUs by default",
+        value_parser = clap::value_parser!(NonZeroUsize)
+    )]
+    pub threads: Option<NonZeroUsize>,
+
+    #[arg(
+        name = "text",
+        short = 'a',
+        long = "text",
+        help = "Search binary files as if they were text"
+    )]
+    pub text: bool,
+
+    #[arg(
+        name = "skip-binary",
+        short = 'I',
+        help = "Skip binary files",
+        conflicts_with = "text"
+    )]
+    pub skip_binary: bool,
+
+    #[arg(
+        name = "line-number",
+        short = 'n',
+        long = "line-number",
+        help = "Prefix each line with its 1 based line number"
+    )]
+    pub line_number: bool,
+
+    #[arg(
+        name = "with-filename",
+        short = 'H',
+        long = "with-filename",
+        help = "Prefix each line with the file name",
+        overrides_with = "no-filename"
+    )]
+    pub with_filename: bool,
+
+    #[arg(
+        name = "no-filename",
+        short = 'h',
+        long = "no-filename",
+        help = "Never prefix lines with the file name",
+        overrides_with = "with-filename"
+    )]
+    pub no_filename: bool,
+
+    #[arg(
+        name = "byte-offset",
+        short = 'b',
+        long = "byte-offset",
+        help = "Prefix each line with the 0 based byte offset of its start"
+    )]
+    pub byte_offset: bool,
+
+    #[arg(
+        name = "files-with-matches",
+        short = 'l',
+        long = "files-with-matches",
+        help = "Print only the names of files with selected lines",
+        conflicts_with = "files-without-match"
+    )]
+    pub files_with_matches: bool,
+
+    #[arg(
+        name = "files-without-match",
+        short = 'L',
+        long = "files-without-match",
+        help = "Print only the names of files without selected lines"
+    )]
+    pub files_without_match: bool,
+
+    #[arg(
+        name = "quiet",
+        short = 'q',
+        long = "quiet",
+        visible_alias = "silent",
+        help = "Print nothing, exit with status 0 at the first selected line"
+    )]
+    pub quiet: bool,
+
+    #[arg(
+        name = "no-messages",
+        short = 's',
+        long = "no-messages",
+        help = "Suppress error messages about unreadable files"
+    )]
+    pub no_messages: bool,
+
+    #[arg(
+        name = "max-count",
+        value_name = "NUM",
+        short = 'm',
+        long = "max-count",
+        help = "Stop reading a file after NUM selected lines"
+    )]
+    pub max_count: Option<u64>,
+
+    #[arg(
+        name = "line-buffered",
+        long = "line-buffered",
+        help = "Flush the output after every line, as it does on a terminal"
+    )]
+    pub line_buffered: bool,
+
+    #[arg(
+        name = "color",
+        value_name = "WHEN",
+        long = "color",
+        visible_alias = "colour",
+        help = "Highlight matches, file names, line numbers and separators, colors are set with GREP_COLORS",
+        value_enum,
+        num_args = 0..=1,
+        require_equals = true,
+        default_value_t = When::Never,
+        default_missing_value = "auto"
+    )]
+    pub color: When,
+
+    #[arg(
+        name = "after-context",
+        value_name = "NUM",
+        short = 'A',
+        long = "after-context",
+        help = "Print NUM lines of trailing context"
+    )]
+    pub after_context: Option<usize>,
+
+    #[arg(
+        name = "before-context",
+        value_name = "NUM",
+        short = 'B',
+        long = "before-context",
+        help = "Print NUM lines of leading context"
+    )]
+    pub before_context: Option<usize>,
+
+    #[arg(
+        name = "context",
+        value_name = "NUM",
+        short = 'C',
+        long = "context",
+        help = "Print NUM lines of leading and trailing context"
+    )]
+    pub context: Option<usize>,
+
+    #[arg(
+        name = "json",
+        long = "json",
+        help = "Print the results as JSON Lines messages, in the format of ripgrep",
+        conflicts_with_all = ["count", "files-with-matches", "files-without-match", "quiet", "only-matching"]
+    )]
+    pub json: bool,
+
+    #[arg(
+        name = "replace",
+        value_name = "TEMPLATE",
+        long = "replace",
+        help = "Print matching lines with every match replaced by TEMPLATE, where $1 or ${name} stand for a group of the match",
+        conflicts_with = "json"
+    )]
+    pub replace: Option<String>,
+
+    #[arg(
+        name = "in-place",
+        long = "in-place",
+        help = "Write the replacements to the files, and print the number of replacements in every changed file",
+        requires = "replace",
+        conflicts_with_all = ["count", "files-with-matches", "files-without-match", "quiet", "only-matching"]
+    )]
+    pub in_place: bool,
+
+    #[command(flatten)]
+    pub decompress: Decompress,
+
+    #[command(flatten)]
+    pub terminator: Terminator,
+
+    // -h is --no-filename, as in GNU grep
+    #[arg(name = "help", long = "help", help = "Print help", action = ArgAction::Help)]
+    pub help: Option<bool>,
+}
+
+/// When to use colors
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum When {
+    Never,
+    Always,
+    /// When standard output is a terminal
+    Auto,
+}
+
+/// A selected or context line and where it was found
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Line<'a> {
+    /// 1 based line number
+    pub number: u64,
+    /// Byte offset of the start of the line
+    pub offset: u64,
+    /// The line, terminator included
+    pub bytes: &'a [u8],
+    /// Not selected, printed around a selected line
+    pub context: bool,
+    /// With -U, the matches that may overlap the line, which the line
+    /// alone may not match
+    pub matches: Option<Matches<'a>>,
+}
+
+/// Matches found in a whole input, by byte offset in it
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Matches<'a> {
+    pub input: &'a [u8],
+    pub spans: &'a [Range<usize>],
+}
+
+/// The patterns a `Searcher` looks for
+#[derive(Clone, Debug)]
+pub enum Matcher {
+    Regex(Regex),
+    /// Fixed strings, all searched at once with Aho-Corasick
+    Literals(AhoCorasick),
+}
+
+/// How the patterns of a `Matcher` match
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MatchOptions {
+    pub fixed_strings: bool,
+    pub ignore_case: bool,
+    /// Matches must not touch a word character on either side
+    pub word_regexp: bool,
+    /// Matches must span the whole line
+    pub line_regexp: bool,
+    /// Capture groups are needed, to expand replacements
+    pub captures: bool,
+    /// ^ and $ match at the start and end of every line of the haystack
+    pub multiline: bool,
+}
+
+impl Matcher {
+    /// Builds a matcher for any of `patterns`. A line matches when one of
+    /// them does; an empty list matches nothing
+    pub fn new(patterns: &[Vec<u8>], options: MatchOptions) -> Result<Self> {
+        let MatchOptions {
+            fixed_strings,
+            ignore_case,
+            word_regexp,
+            line_regexp,
+            captures,
+            multiline,
+        } = options;
+
+        // Aho-Corasick only folds ASCII case and has no anchors nor
+        // groups, the regex engine does all three
+        let unicode_case = ignore_case && !patterns.iter().all(|p| p.is_ascii());
+        let anchored = word_regexp || line_regexp;
+        if fixed_strings && !unicode_case && !anchored && !captures || patterns.is_empty() {
+            return AhoCorasick::builder()
+                .ascii_case_insensitive(ignore_case)
+                .match_kind(MatchKind::LeftmostLongest)
+                .build(patterns)
+                .map(Self::Literals)
+                .map_err(|e| Error::usage("grep", e.to_string()));
+        }
+
+        let patterns = patterns
+            .iter()
+            .map(|p| match std::str::from_utf8(p) {
+                Ok(p) if fixed_strings => Ok(regex::escape(p)),
+                Ok(p) => Ok(p.to_string()),
+                Err(_) => Err(Error::usage("grep", "invalid UTF-8 in pattern")),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let mut pattern = match patterns.as_slice() {
+            [pattern] => pattern.clone(),
+            patterns => patterns
+                .iter()
+                .map(|p| format!("(?:{p})"))
+                .collect::<Vec<_>>()
+                .join("|"),
+        };
+
+        if line_regexp {
+            pattern = format!("^(?:{pattern})$");
+        } else if word_regexp {
+            // As in GNU grep, a match may begin or end with a non-word
+            // character, like "-x", as long as it is not glued to a word
+            pattern = format!(r"\b{{start-half}}(?:{pattern})\b{{end-half}}");
+        }
+
+        RegexBuilder::new(&pattern)
+            .case_insensitive(ignore_case)
+            .multi_line(multiline)
+            .build()
+            .map(Self::Regex)
+            .map_err(|e| Error::usage("grep", e.to_string()))
+    }
+
+    pub fn is_match(&self, haystack: &[u8]) -> bool {
+        match self {
+            Self::Regex(regex) => regex.is_match(haystack),
+            Self::Literals(literals) => literals.is_match(haystack),
+        }
+    }
+
+    /// The first match that starts at or after `start`. Anchors and word
+    /// boundaries still see the text before `start`
+    pub fn find_at(&self, haystack: &[u8], start: usize) -> Option<Range<usize>> {
+        match self {
+            Self::Regex(regex) => regex.find_at(haystack, start).map(|m| m.range()),
+            Self::Literals(literals) => literals
+                .find(aho_corasick::Input::new(haystack).span(start..haystack.len()))
+                .map(|m| m.range()),
+        }
+    }
+
+    /// The non-empty matches in `haystack`, left to right
+    pub fn find_iter<'a>(&'a self, haystack: &'a [u8]) -> impl Iterator<Item = Range<usize>> + 'a {
+        let mut start = 0;
+        iter::from_fn(move || {
+            while start <= haystack.len() {
+                let m = self.find_at(haystack, start)?;
+                if m.is_empty() {
+                    start = m.start + 1;
+                    continue;
+                }
+
+                start = m.end;
+                return Some(m);
+            }
+
+            None
+        })
+    }
+
+    /// Appends to `dst` the replacement of the match at `span`, found by
+    /// `find_at` or `find_iter`: `template` where $1 or ${name} stand for
+    /// the groups of the match, as in `Regex::replace`. Literals have no
+    /// groups, their replacement is `template` as is
+    pub fn expand(&self, haystack: &[u8], span: Range<usize>, template: &[u8], dst: &mut Vec<u8>) {
+        match self {
+            Self::Regex(regex) => {
+                if let Some(captures) = regex.captures_at(haystack, span.start) {
+                    captures.expand(template, dst);
+                }
+            }
+            Self::Literals(_) => dst.extend_from_slice(template),
+        }
+    }
+}
+
+impl From<Regex> for Matcher {
+    fn from(regex: Regex) -> Self {
+        Self::Regex(regex)
+    }
+}
+
+/// Selects the lines of an input that match a pattern. This is grep
+/// without the command line, it works on any `BufRead`
+#[derive(Clone, Debug)]
+pub struct Searcher {
+    matcher: Matcher,
+    invert_match: bool,
+    terminator: u8,
+    before_context: usize,
+    after_context: usize,
+    max_count: Option<u64>,
+    multiline: bool,
+}
+
+impl Searcher {
+    pub fn new(matcher: impl Into<Matcher>) -> Self {
+        Self {
+            matcher: matcher.into(),
+            invert_match: false,
+            terminator: b'\n',
+            before_context: 0,
+            after_context: 0,
+            max_count: None,
+            multiline: false,
+        }
+    }
+
+    /// Select the lines that do not match instead
+    pub fn invert_match(mut self, yes: bool) -> Self {
+        self.invert_match = yes;
+        self
+    }
+
+    /// Byte that ends every line, '\n' by default
+    pub fn terminator(mut self, byte: u8) -> Self {
+        self.terminator = byte;
+        self
+    }
+
+    /// Also report up to `lines` lines before every selected line
+    pub fn before_context(mut self, lines: usize) -> Self {
+        self.before_context = lines;
+        self
+    }
+
+    /// Also report up to `lines` lines after every selected line
+    pub fn after_context(mut self, lines: usize) -> Self {
+        self.after_context = lines;
+        self
+    }
+
+    /// Stop after `lines` selected lines, once their after context is
+    /// reported
+    pub fn max_count(mut self, lines: Option<u64>) -> Self {
+        self.max_count = lines;
+        self
+    }
+
+    /// Match the whole input at once rather than line by line, and select
+    /// every line a match touches. The input is read in memory
+    pub fn multiline(mut self, yes: bool) -> Self {
+        self.multiline = yes;
+        self
+    }
+
+    pub fn matcher(&self) -> &Matcher {
+        &self.matcher
+    }
+
+    /// Whether `line` is selected. Its terminator is not part of the
+    /// match, so that `$` and -x match at the end of the line
+    pub fn is_match(&self, line: &[u8]) -> bool {
+        self.matcher.is_match(chomp(line, self.terminator)) ^ self.invert_match
+    }
+
+    /// Calls `sink` with every selected line of `content`, and the context
+    /// lines around them, until it returns `false`
+    pub fn search<R, F>(&self, mut content: R, mut sink: F) -> io::Result<()>
+    where
+        R: BufRead,
+        F: FnMut(Line) -> io::Result<bool>,
+    {
+        if self.max_count == Some(0) {
+            return Ok(());
+        }
+
+        if self.multiline {
+            let mut buf = Vec::new();
+            content.read_to_end(&mut buf)?;
+            return self.search_in(&buf, sink);
+        }
+
+        let mut window = Window::new(self);
+        let mut content = LineReader::with_delimiter(content, self.terminator);
+        let (mut number, mut offset) = (0, 0);
+        while let Some(line) = content.next_line()? {
+            number += 1;
+            let selected = self.is_match(line);
+            if !window.feed(selected, number, offset, line, None, &mut sink)? {
+                break;
+            }
+
+            offset += line.len() as u64;
+        }
+
+        Ok(())
+    }
+
+    /// Like `search`, over input that is already in memory
+    pub fn search_in<F>(&self, content: &[u8], mut sink: F) -> io::Result<()>
+    where
+        F: FnMut(Line) -> io::Result<bool>,
+    {
+        if self.max_count == Some(0) {
+            return Ok(());
+        }
+
+        let mut window = Window::new(self);
+        let mut touched = self.multiline.then(|| self.touched(content).fuse());
+        // The matches of the current line are found[first..last]
+        let found: Vec<_> = match self.multiline {
+            true => self.matcher.find_iter(content).collect(),
+            false => Vec::new(),
+        };
+        let (mut first, mut last) = (0, 0);
+        let mut lines = 0..0;
+        let (mut number, mut start) = (0, 0);
+        while start < content.len() {
+            let end =
+                memchr(self.terminator, &content[start..]).map_or(content.len(), |i| start + i + 1);
+            let line = &content[start..end];
+            let selected = match &mut touched {
+                Some(touched) => {
+                    while lines.end <= start {
+                        match touched.next() {
+                            Some(next) => lines = next,
+                            None => break,
+                        }
+                    }
+                    lines.contains(&start) ^ self.invert_match
+                }
+                None => self.is_match(line),
+            };
+
+            let matches = self.multiline.then(|| {
+                while found.get(first).is_some_and(|m| m.end <= start) {
+                    first += 1;
+                }
+                last = last.max(first);
+                while found.get(last).is_some_and(|m| m.start < end) {
+                    last += 1;
+                }
+                Matches {
+                    input: content,
+                    spans: &found[first..last],
+                }
+            });
+
+            number += 1;
+            if !window.feed(selected, number, start as u64, line, matches, &mut sink)? {
+                break;
+            }
+
+            start = end;
+        }
+
+        Ok(())
+    }
+
+    /// The bytes of the lines touched by every match in `content`, in
+    /// order. A match that ends with a terminator does not touch the next
+    /// line, an empty match touches the line it is in
+    fn touched<'a>(&'a self, content: &'a [u8]) -> impl Iterator<Item = Range<usize>> + 'a {
+        let mut start = 0;
+        iter::from_fn(move || {
+            if start > content.len() {
+                return None;
+            }
+
+            let m = self.matcher.find_at(content, start)?;
+            let last = if m.is_empty() { m.start } else { m.end - 1 };
+            let from = memrchr(self.terminator, &content[..m.start]).map_or(0, |i| i + 1);
+            let to =
+                memchr(self.terminator, &content[last..]).map_or(content.len(), |i| last + i + 1);
+            // A match may start on the last line touched and go further
+            start = if m.is_empty() { m.start + 1 } else { m.end };
+
+            Some(from..to)
+        })
+    }
+}
+
+/// Sliding window of the lines around the selected ones: the last
+/// `before` unreported lines, and how many lines of after context are due
+struct Window<'a> {
+    before: usize,
+    after: usize,
+    previous: VecDeque<(u64, u64, Vec<u8>, Option<Matches<'a>>)>,
+    after_left: usize,
+    /// Selected lines left before the search stops
+    selected_left: Option<u64>,
+}
+
+impl<'a> Window<'a> {
+    fn new(searcher: &Searcher) -> Self {
+        Self {
+            before: searcher.before_context,
+            after: searcher.after_context,
+            previous: VecDeque::with_capacity(searcher.before_context),
+            after_left: 0,
+            selected_left: searcher.max_count,
+        }
+    }
+
+    /// Reports `bytes` to `sink` if it is selected or in a context.
+    /// Returns `false` when the sink asks to stop
+    fn feed<F>(
+        &mut self,
+        selected: bool,
+        number: u64,
+        offset: u64,
+        bytes: &[u8],
+        matches: Option<Matches<'a>>,
+        sink: &mut F,
+    ) -> io::Result<bool>
+    where
+        F: FnMut(Line) -> io::Result<bool>,
+    {
+        let line = |context| Line {
+            number,
+            offset,
+            bytes,
+            context,
+            matches,
+        };
+
+        // Past the maximum, only the trailing context is left, selected or not
+        if self.selected_left == Some(0) {
+            if self.after_left == 0 {
+                return Ok(false);
+            }
+
+            self.after_left -= 1;
+            return sink(line(true));
+        }
+
+        if selected {
+            if let Some(left) = &mut self.selected_left {
+                *left -= 1;
+            }
+
+            for (number, offset, bytes, matches) in self.previous.drain(..) {
+                let before = Line {
+                    number,
+                    offset,
+                    bytes: &bytes,
+                    context: true,
+                    matches,
+                };
+                if !sink(before)? {
+                    return Ok(false);
+                }
+            }
+
+            self.after_left = self.after;
+            if !sink(line(false))? {
+                return Ok(false);
+            }
+
+            // Stop right away when no trailing context is due
+            return Ok(self.selected_left != Some(0) || self.after_left > 0);
+        }
+
+        if self.after_left > 0 {
+            self.after_left -= 1;
+            return sink(line(true));
+        }
+
+        if self.before > 0 {
+            // Reuse the allocation of the line that falls out of the window
+            let mut buf = match self.previous.len() == self.before {
+                true => self.previous.pop_front().map(|(_, _, buf, _)| buf),
+                false => None,
+            }
+            .unwrap_or_default();
+            buf.clear();
+            buf.extend_from_slice(bytes);
+            self.previous.push_back((number, offset, buf, matches));
+        }
+
+        Ok(true)
+    }
+}
+
+pub fn run(args: Args) -> Result<()> {
+    // Counting, listing and -o ignore context lines
+    let lines_printed = !(args.count
+        || args.quiet
+        || args.files_with_matches
+        || args.files_without_match
+        || args.only_matching);
+    let (before, after) = match lines_printed {
+        true => (
+            args.before_context.or(args.context).unwrap_or(0),
+            args.after_context.or(args.context).unwrap_or(0),
+        ),
+        false => (0, 0),
+    };
+
+    let options = MatchOptions {
+        fixed_strings: args.fixed_strings,
+        ignore_case: args.ignore_case,
+        word_regexp: args.word_regexp,
+        line_regexp: args.line_regexp,
+        captures: args.replace.is_some(),
+        multiline: args.multiline,
+    };
+    let matcher = Matcher::new(&patterns(&args)?, options)?;
+    let searcher = Searcher::new(matcher)
+        .invert_match(args.invert_match)
+        .terminator(args.terminator.byte())
+        .before_context(before)
+        .after_context(after)
+        .max_count(args.max_count)
+        .multiline(args.multiline);
+    let walk = Walk::new(&args)?;
+    let with_filename = if args.with_filename || args.no_filename {
+        args.with_filename
+    } else {
+        // A directory searched with -r holds several files
+        args.files.len() > 1 || args.recursive && args.files.iter().any(|f| Path::new(f).is_dir())
+    };
+
+    // GNU grep separates groups even for an explicit -A 0
+    let context = args.after_context.or(args.before_context).or(args.context);
+    let colors = match args.color {
+        When::Always => true,
+        When::Never => false,
+        When::Auto => stdout_is_terminal() && env::var("TERM").map_or(true, |t| t != "dumb"),
+    }
+    .then(|| Colors::parse(&env::var("GREP_COLORS").unwrap_or_default()));
+    let group_separator = lines_printed && context.is_some() && !args.json;
+    let mut printer =
+        Printer::new(Output::new(), &args, group_separator, searcher.matcher()).colors(colors);
+
+    let files = find_files(&args.files, &walk);
+    let threads = args
+        .threads
+        .or_else(|| thread::available_parallelism().ok())
+        .map_or(1, NonZeroUsize::get);
+    let mut status = Status::default();
+    // Workers could not read "-" in a pipeline, whose standard input is
+    // thread local
+    if args.recursive && threads > 1 && !args.files.iter().any(|f| f == "-") {
+        search_parallel(
+            files,
+            threads,
+            &searcher,
+            with_filename,
+            &args,
+            &mut printer,
+            &mut status,
+        )
+    } else {
+        for f in files {
+            let result = f.and_then(|filename| {
+                grep(&filename, &searcher, with_filename, &args, &mut printer)
+            });
+            if status.update(result, &args) {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+    .and_then(|_| match args.json {
+        true => printer.print_summary(),
+        false => Ok(()),
+    })
+    .and_then(|_| printer.out.flush())
+    .map_err(|e| Error::io("grep", "standard output", e))?;
+
+    // Any selected line is enough for -q, even after errors
+    if status.matched && args.quiet {
+        return Ok(());
+    }
+
+    if status.failed {
+        return Err(Error::Status(2));
+    }
+
+    if !status.matched {
+        return Err(Error::Status(1));
+    }
+
+    Ok(())
+}
+
+/// Whether any file matched or failed so far
+#[derive(Debug, Default)]
+struct Status {
+    matched: bool,
+    failed: bool,
+}
+
+impl Status {
+    /// Records the result of searching a file, and reports its error.
+    /// Returns whether the search is over, at the first match with -q
+    fn update(&mut self, result: Result<bool>, args: &Args) -> bool {
+        match result {
+            Ok(selected) => self.matched |= selected,
+            Err(e) => {
+                if !args.no_messages {
+                    eprintln!("{e}");
+                }
+                self.failed = true;
+            }
+        }
+
+        self.matched && args.quiet
+    }
+}
+
+/// Output of a file searched by a worker thread
+struct Report {
+    output: Vec<u8>,
+    /// Lines were printed, after a group separator if others came before
+    grouped: bool,
+    stats: Stats,
+    result: Result<bool>,
+}
+
+/// Searches `files` on `threads` worker threads. The output of every
+/// file is buffered, then written in the order of `files` as soon as the
+/// files before it are done, so that it does not depend on scheduling
+fn search_parallel(
+    files: impl Iterator<Item = Result<String>> + Send,
+    threads: usize,
+    searcher: &Searcher,
+    with_filename: bool,
+    args: &Args,
+    printer: &mut Printer<Output>,
+    status: &mut Status,
+) -> io::Result<()> {
+    let stop = AtomicBool::new(false);
+    let stop = &stop;
+    thread::scope(|scope| {
+        let (job_tx, job_rx) = sync_channel(threads);
+        let (report_tx, report_rx) = channel();
+
+        // The walk ends once the workers are gone and the jobs receiver
+        // with them
+        scope.spawn(move || {
+            for job in files.enumerate() {
+                if stop.load(Ordering::Relaxed) || job_tx.send(job).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let job_rx = Arc::new(Mutex::new(job_rx));
+        for _ in 0..threads {
+            let job_rx = Arc::clone(&job_rx);
+            let report_tx = report_tx.clone();
+            let mut buffer = printer.buffered();
+            scope.spawn(move || loop {
+                let job = job_rx.lock().unwrap().recv();
+                let Ok((i, file)) = job else {
+                    break;
+                };
+                if stop.load(Ordering::Relaxed) {
+                    break;
+                }
+
+                // Whether a separator goes first depends on the files
+                // before, only known when writing
+                buffer.printed = false;
+                let result = file.and_then(|filename| {
+                    grep(&filename, searcher, with_filename, args, &mut buffer)
+                });
+                let report = Report {
+                    output: mem::take(&mut buffer.out),
+                    grouped: buffer.printed,
+                    stats: mem::take(&mut buffer.total),
+                    result,
+                };
+                if report_tx.send((i, report)).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(job_rx);
+        drop(report_tx);
+
+        let mut pending = BTreeMap::new();
+        let mut next = 0;
+        for (i, report) in report_rx {
+            pending.insert(i, report);
+            while let Some(report) = pending.remove(&next) {
+                next += 1;
+                if report.grouped {
+                    printer.start_group()?;
+                }
+                printer.out.write_all(&report.output)?;
+                printer.total += report.stats;
+                if printer.line_buffered {
+                    printer.out.flush()?;
+                }
+
+                if status.update(report.result, args) {
+                    stop.store(true, Ordering::Relaxed);
+                    return Ok(());
+                }
+            }
+        }
+
+        Ok(())
+    })
+}
+
+/// Bytes at the start of a file checked for NUL bytes, which make it a
+/// binary file
+const BINARY_PEEK: usize = 8192;
+
+/// Searches one file and prints its lines, count or name. Returns whether
+/// it had selected lines, which make the exit status 0. As in GNU grep
+/// since 3.5, that holds for -L too, whether the file was listed or not
+fn grep<W: Write>(
+    filename: &str,
+    searcher: &Searcher,
+    with_filename: bool,
+    args: &Args,
+    printer: &mut Printer<W>,
+) -> Result<bool> {
+    let io_err = |e| Error::io("grep", filename, e);
+    let out_err = |e| Error::io("grep", "standard output", e);
+    let name = if filename == "-" {
+        "(standard input)"
+    } else {
+        filename
+    };
+    let prefix = with_filename.then_some(name);
+
+    if args.in_place {
+        let count = replace_in_place(filename, searcher, args)?;
+        if count > 0 {
+            printer.print_count(Some(name), count).map_err(out_err)?;
+        }
+
+        return Ok(count > 0);
+    }
+
+    // The first selected line decides -q, -l and -L
+    let list = args.files_with_matches || args.files_without_match;
+    let stop_early = args.quiet || list;
+    let mut count = 0;
+    let mut written = Ok(());
+    printer.start_file(name);
+
+    // NUL bytes mark binary files, unless they end lines with -z
+    let detect_binary = !args.text && args.terminator.byte() != b'\0';
+    let mut input = open_input(filename, args.decompress.enabled()).map_err(io_err)?;
+    let mut binary = detect_binary
+        && match &mut input {
+            Input::Mapped(map) => memchr(0, &map[..map.len().min(BINARY_PEEK)]).is_some(),
+            Input::Stream(content) => memchr(0, content.fill_buf().map_err(io_err)?).is_some(),
+        };
+
+    let skip = binary && args.skip_binary;
+    let sink = |line: Line| {
+        if !line.context {
+            count += 1;
+        }
+        if stop_early || args.count {
+            return Ok(!stop_early);
+        }
+
+        // Lines of binary files are not printed, the first selected one
+        // ends the search. A NUL byte further in makes the file binary too
+        binary |= detect_binary && !line.context && memchr(0, line.bytes).is_some();
+        if binary {
+            return Ok(line.context);
+        }
+
+        written = printer.print(prefix, &line);
+        Ok(written.is_ok())
+    };
+
+    if !skip {
+        match input {
+            Input::Mapped(map) => searcher.search_in(&map, sink),
+            Input::Stream(content) => searcher.search(content, sink),
+        }
+        .map_err(io_err)?;
+        written.map_err(out_err)?;
+    }
+
+    if binary && args.skip_binary {
+        count = 0;
+    }
+    let selected = count > 0;
+    if args.quiet {
+        return Ok(selected);
+    }
+
+    if list {
+        if selected == args.files_with_matches {
+            printer.print_name(name).map_err(out_err)?;
+        }
+
+        return Ok(selected);
+    }
+
+    if args.count {
+        printer.print_count(prefix, count).map_err(out_err)?;
+    } else if binary && selected {
+        printer.print_binary(name).map_err(out_err)?;
+    }
+    printer.end_file().map_err(out_err)?;
+
+    Ok(selected)
+}
+
+/// Replaces the matches in the selected lines of `filename` with the
+/// --replace template, and returns the number of replacements. The file
+/// is written to a temporary file next to it, with the same owner, group
+/// and mode, then renamed over it, so that it is never seen half written
+fn replace_in_place(filename: &str, searcher: &Searcher, args: &Args) -> Result<u64> {
+    let io_err = |e| Error::io("grep", filename, e);
+    if filename == "-" {
+        return Err(Error::usage(
+            "grep",
+            "--in-place cannot rewrite standard input",
+        ));
+    }
+
+    // Binary files are left alone, as their lines are not printed either
+    let terminator = args.terminator.byte();
+    let content = fs::read(filename).map_err(io_err)?;
+    if !args.text && terminator != b'\0' && memchr(0, &content).is_some() {
+        return Ok(0);
+    }
+
+    let template = args.replace.as_deref().unwrap_or_default().as_bytes();
+    let matcher = searcher.matcher();
+    let mut output = Vec::with_capacity(content.len());
+    let mut count = 0;
+    if args.multiline && !args.invert_match {
+        count = replace_all(matcher, &content, template, &mut output);
+    } else {
+        let mut selected = 0;
+        for line in content.split_inclusive(|&b| b == terminator) {
+            // Lines selected with -v have no match to replace
+            let below_max = args.max_count.is_none_or(|max| selected < max);
+            if args.invert_match || !below_max || !searcher.is_match(line) {
+                output.extend_from_slice(line);
+                continue;
+            }
+
+            selected += 1;
+            let text = chomp(line, terminator);
+            count += replace_all(matcher, text, template, &mut output);
+            output.extend_from_slice(&line[text.len()..]);
+        }
+    }
+
+    if count == 0 {
+        return Ok(0);
+    }
+
+    // Through a symlink, the target is edited and the link kept
+    let path = fs::canonicalize(filename).map_err(io_err)?;
+    let metadata = fs::metadata(&path).map_err(io_err)?;
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let temp = path.with_file_name(format!(".{name}.grep-{}", process::id()));
+    let written = fs::File::options()
+        .write(true)
+        .create_new(true)
+        .open(&temp)
+        .and_then(|mut file| {
+            file.write_all(&output)?;
+            // A file that cannot keep its owner and group is not edited.
+            // The mode is set last, as a change of owner clears setuid
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::{fchown, MetadataExt};
+                fchown(&file, Some(metadata.uid()), Some(metadata.gid()))?;
+            }
+            file.set_permissions(metadata.permissions())?;
+            file.sync_all()
+        })
+        .and_then(|_| fs::rename(&temp, &path));
+    if written.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    written.map_err(io_err)?;
+
+    Ok(count)
+}
+
+/// Appends `text` to `output` with its matches replaced, and returns the
+/// number of replacements
+fn replace_all(matcher: &Matcher, text: &[u8], template: &[u8], output: &mut Vec<u8>) -> u64 {
+    let mut count = 0;
+    let mut pos = 0;
+    for span in matcher.find_iter(text) {
+        output.extend_from_slice(&text[pos..span.start]);
+        pos = span.end;
+        matcher.expand(text, span, template, output);
+        count += 1;
+    }
+    output.extend_from_slice(&text[pos..]);
+
+    count
+}
+
+/// SGR sequences of the --color output. GREP_COLORS changes them as in
+/// GNU grep, e.g. "ms=01;31:fn=35:ln=32:se=36:ne"
+#[derive(Clone, Debug, PartialEq)]
+struct Colors {
+    selected_match: String,
+    context_match: String,
+    selected_line: String,
+    context_line: String,
+    filename: String,
+    line_number: String,
+    byte_offset: String,
+    separator: String,
+    /// sl and cx swap roles with -v
+    reverse: bool,
+    /// Do not clear to the end of line after every sequence
+    no_erase: bool,
+}
+
+impl Default for Colors {
+    fn default() -> Self {
+        Self {
+            selected_match: "01;31".to_string(),
+            context_match: "01;31".to_string(),
+            selected_line: String::new(),
+            context_line: String::new(),
+            filename: "35".to_string(),
+            line_number: "32".to_string(),
+            byte_offset: "32".to_string(),
+            separator: "36".to_string(),
+            reverse: false,
+            no_erase: false,
+        }
+    }
+}
+
+impl Colors {
+    /// Applies a GREP_COLORS value over the defaults. Unknown entries are
+    /// ignored
+    fn parse(spec: &str) -> Self {
+        let mut colors = Self::default();
+        for entry in spec.split(':') {
+            let (key, value) = entry.split_once('=').unwrap_or((entry, ""));
+            let value = value.to_string();
+            match key {
+                "mt" => {
+                    colors.selected_match = value.clone();
+                    colors.context_match = value;
+                }
+                "ms" => colors.selected_match = value,
+                "mc" => colors.context_match = value,
+                "sl" => colors.selected_line = value,
+                "cx" => colors.context_line = value,
+                "fn" => colors.filename = value,
+                "ln" => colors.line_number = value,
+                "bn" => colors.byte_offset = value,
+                "se" => colors.separator = value,
+                "rv" => colors.reverse = true,
+                "ne" => colors.no_erase = true,
+                _ => (),
+            }
+        }
+
+        colors
+    }
+}
+
+/// Writes selected and context lines with their prefixes, and "--"
+/// between groups of lines that are not adjacent
+struct Printer<W> {
+    out: W,
+    line_number: bool,
+    byte_offset: bool,
+    terminator: u8,
+    invert_match: bool,
+    group_separator: bool,
+    /// Flush every line, so that matches in a growing input, as in
+    /// `tail -f log | grep x`, show up right away
+    line_buffered: bool,
+    /// Print only the matches, found with `matcher`
+    only_matching: bool,
+    colors: Option<Colors>,
+    /// Finds the text to highlight, or to print with -o
+    matcher: Matcher,
+    /// Template of --replace
+    replace: Option<Vec<u8>>,
+    /// Number of the last line printed from the current file
+    last: Option<u64>,
+    /// A group was printed already, from any file
+    printed: bool,
+    /// Print JSON messages instead of lines
+    json: bool,
+    /// Name of the current file in JSON messages
+    path: String,
+    /// The begin message of the current file was printed
+    begun: bool,
+    /// The current file is binary and its lines were not printed
+    binary: bool,
+    /// Statistics of the current file, and of the files done
+    stats: Stats,
+    total: Stats,
+}
+
+impl<W: Write> Printer<W> {
+    fn new(out: W, args: &Args, group_separator: bool, matcher: &Matcher) -> Self {
+        Self {
+            out,
+            line_number: args.line_number,
+            byte_offset: args.byte_offset,
+            terminator: args.terminator.byte(),
+            invert_match: args.invert_match,
+            group_separator,
+            line_buffered: args.line_buffered || stdout_is_terminal(),
+            only_matching: args.only_matching,
+            colors: None,
+            matcher: matcher.clone(),
+            replace: args.replace.as_ref().map(|t| t.as_bytes().to_vec()),
+            last: None,
+            printed: false,
+            json: args.json,
+            path: String::new(),
+            begun: false,
+            binary: false,
+            stats: Stats::default(),
+            total: Stats::default(),
+        }
+    }
+
+    fn colors(mut self, colors: Option<Colors>) -> Self {
+        self.colors = colors;
+        self
+    }
+
+    /// A printer with the same settings, that writes to a buffer
+    fn buffered(&self) -> Printer<Vec<u8>> {
+        Printer {
+            out: Vec::new(),
+            line_number: self.line_number,
+            byte_offset: self.byte_offset,
+            terminator: self.terminator,
+            invert_match: self.invert_match,
+            group_separator: self.group_separator,
+            line_buffered: self.line_buffered,
+            only_matching: self.only_matching,
+            colors: self.colors.clone(),
+            matcher: self.matcher.clone(),
+            replace: self.replace.clone(),
+            last: None,
+            printed: false,
+            json: self.json,
+            path: String::new(),
+            begun: false,
+            binary: false,
+            stats: Stats::default(),
+            total: Stats::default(),
+        }
+    }
+
+    fn start_file(&mut self, name: &str) {
+        self.last = None;
+        self.path.clear();
+        self.path.push_str(name);
+        self.begun = false;
+        self.binary = false;
+        self.stats = Stats {
+            searches: 1,
+            ..Default::default()
+        };
+    }
+
+    /// Prints the end message of the current file, if it had lines, and
+    /// adds its statistics to the total
+    fn end_file(&mut self) -> io::Result<()> {
+        self.stats.searches_with_match = u64::from(self.stats.matched_lines > 0 || self.binary);
+        if self.begun {
+            let path = Data::from(self.path.as_bytes());
+            write_json(
+                &mut self.out,
+                &Message::End {
+                    path,
+                    binary: self.binary,
+                    stats: self.stats,
+                },
+            )?;
+        }
+        self.total += self.stats;
+
+        Ok(())
+    }
+
+    /// Prints the begin message of the current file, before its first line
+    fn begin(&mut self) -> io::Result<()> {
+        if !self.begun {
+            let path = Data::from(self.path.as_bytes());
+            write_json(&mut self.out, &Message::Begin { path })?;
+            self.begun = true;
+        }
+
+        Ok(())
+    }
+
+    fn print_summary(&mut self) -> io::Result<()> {
+        write_json(&mut self.out, &Message::Summary { stats: self.total })
+    }
+
+    /// Writes "--" before a group of lines, unless it is the first one
+    fn start_group(&mut self) -> io::Result<()> {
+        if self.group_separator && self.printed {
+            self.paint(|c| &c.separator, b"--")?;
+            self.out.write_all(b"\n")?;
+        }
+        self.printed = true;
+
+        Ok(())
+    }
+
+    /// Starts the color picked by `color`, if colors are on and it is set.
+    /// Returns whether it did
+    fn start_color(&mut self, color: fn(&Colors) -> &str) -> io::Result<bool> {
+        match &self.colors {
+            Some(colors) if !color(colors).is_empty() => {
+                let erase = if colors.no_erase { "" } else { "\x1b[K" };
+                write!(self.out, "\x1b[{}m{erase}", color(colors))?;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    fn end_color(&mut self) -> io::Result<()> {
+        match &self.colors {
+            Some(colors) if colors.no_erase => write!(self.out, "\x1b[m"),
+            _ => write!(self.out, "\x1b[m\x1b[K"),
+        }
+    }
+
+    /// Writes `text` in the color picked by `color`
+    fn paint(&mut self, color: fn(&Colors) -> &str, text: &[u8]) -> io::Result<()> {
+        let started = self.start_color(color)?;
+        self.out.write_all(text)?;
+        if started {
+            self.end_color()?;
+        }
+
+        Ok(())
+    }
+
+    fn print(&mut self, filename: Option<&str>, line: &Line) -> io::Result<()> {
+        if self.json {
+            return self.print_json(line);
+        }
+        if self.only_matching {
+            return self.print_matches(filename, line);
+        }
+
+        let adjacent = self.last.is_some_and(|last| line.number == last + 1);
+        if !adjacent {
+            self.start_group()?;
+        }
+        self.last = Some(line.number);
+
+        // Selected lines are marked with ':', context lines with '-'
+        let sep = if line.context { b"-" } else { b":" };
+        self.print_prefix(filename, line.number, line.offset, sep)?;
+
+        self.print_content(line)?;
+        self.end_line()
+    }
+
+    /// Every match of a selected line on its own line, for -o. With -v the
+    /// selected lines have no match, and nothing is printed
+    fn print_matches(&mut self, filename: Option<&str>, line: &Line) -> io::Result<()> {
+        if line.context || self.invert_match {
+            return Ok(());
+        }
+
+        let content = chomp(line.bytes, self.terminator);
+        let mut replacement = Vec::new();
+        for (span, whole) in self.spans(line) {
+            // The rest of a multiline match is replaced where it starts
+            if self.replace.is_some() && whole.is_none() {
+                continue;
+            }
+
+            // -b gives the offset of the match rather than of the line
+            let offset = line.offset + span.start as u64;
+            self.print_prefix(filename, line.number, offset, b":")?;
+            let text = self.replacement(line, content, span, whole, &mut replacement);
+            self.paint(|c| &c.selected_match, text)?;
+            self.end_line()?;
+        }
+
+        Ok(())
+    }
+
+    /// The matches in `line` without its terminator, each with the whole
+    /// match in the haystack of `Matcher::expand` when it starts there.
+    /// With -U, the matches found in the whole input are cut to the line
+    fn spans(&self, line: &Line) -> Vec<(Range<usize>, Option<Range<usize>>)> {
+        let content = chomp(line.bytes, self.terminator);
+        let Some(matches) = line.matches else {
+            return self
+                .matcher
+                .find_iter(content)
+                .map(|span| (span.clone(), Some(span)))
+                .collect();
+        };
+
+        let (start, end) = (line.offset as usize, line.offset as usize + content.len());
+        matches
+            .spans
+            .iter()
+            .filter(|m| m.start < end && m.end > start)
+            .map(|m| {
+                let span = m.start.max(start) - start..m.end.min(end) - start;
+                (span, (m.start >= start).then(|| m.clone()))
+            })
+            .collect()
+    }
+
+    /// The text printed for the match at `span` in `content`: the match
+    /// itself, or with --replace the replacement of `whole`, expanded in
+    /// `buf`. The part of a match that starts on another line is replaced
+    /// with nothing
+    fn replacement<'a>(
+        &self,
+        line: &Line<'a>,
+        content: &'a [u8],
+        span: Range<usize>,
+        whole: Option<Range<usize>>,
+        buf: &'a mut Vec<u8>,
+    ) -> &'a [u8] {
+        match (&self.replace, whole) {
+            (Some(template), Some(whole)) => {
+                buf.clear();
+                let haystack = line.matches.map_or(content, |m| m.input);
+                self.matcher.expand(haystack, whole, template, buf);
+                buf
+            }
+            (Some(_), None) => &[],
+            (None, _) => &content[span],
+        }
+    }
+
+    fn print_prefix(
+        &mut self,
+        filename: Option<&str>,
+        number: u64,
+        offset: u64,
+        sep: &[u8],
+    ) -> io::Result<()> {
+        if let Some(name) = filename {
+            self.paint(|c| &c.filename, name.as_bytes())?;
+            self.paint(|c| &c.separator, sep)?;
+        }
+        if self.line_number {
+            self.paint(|c| &c.line_number, number.to_string().as_bytes())?;
+            self.paint(|c| &c.separator, sep)?;
+        }
+        if self.byte_offset {
+            self.paint(|c| &c.byte_offset, offset.to_string().as_bytes())?;
+            self.paint(|c| &c.separator, sep)?;
+        }
+
+        Ok(())
+    }
+
+    fn end_line(&mut self) -> io::Result<()> {
+        self.out.write_all(&[self.terminator])?;
+        if self.line_buffered {
+            self.out.flush()?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes a line without its terminator. The matches are highlighted
+    /// in lines that match: selected lines, or context lines with -v
+    fn print_content(&mut self, line: &Line) -> io::Result<()> {
+        let content = chomp(line.bytes, self.terminator);
+        let selected = !line.context;
+        let reverse = self.colors.as_ref().is_some_and(|c| c.reverse);
+        let line_color: fn(&Colors) -> &str = match selected ^ (reverse && self.invert_match) {
+            true => |c| &c.selected_line,
+            false => |c| &c.context_line,
+        };
+        let match_color: fn(&Colors) -> &str = match selected {
+            true => |c| &c.selected_match,
+            false => |c| &c.context_match,
+        };
+
+        // Matches are found to be highlighted or replaced
+        let matching = selected ^ self.invert_match;
+        let highlight = self
+            .colors
+            .as_ref()
+            .is_some_and(|c| !match_color(c).is_empty());
+        let spans = match matching && (highlight || self.replace.is_some()) {
+            true => self.spans(line),
+            false => Vec::new(),
+        };
+
+        // As in GNU grep, the line color is left on under a match and
+        // started again after it
+        let mut pos = 0;
+        let mut replacement = Vec::new();
+        for (span, whole) in spans {
+            self.start_color(line_color)?;
+            self.out.write_all(&content[pos..span.start])?;
+            pos = span.end;
+            let text = self.replacement(line, content, span, whole, &mut replacement);
+            self.paint(match_color, text)?;
+        }
+
+        match &content[pos..] {
+            [] => Ok(()),
+            tail => self.paint(line_color, tail),
+        }
+    }
+
+    /// File name line of -l and -L
+    fn print_name(&mut self, name: &str) -> io::Result<()> {
+        self.paint(|c| &c.filename, name.as_bytes())?;
+        self.out.write_all(b"\n")
+    }
+
+    /// A match or context message. Submatches are reported in lines that
+    /// match, as highlighted with --color
+    fn print_json(&mut self, line: &Line) -> io::Result<()> {
+        self.begin()?;
+
+        let content = chomp(line.bytes, self.terminator);
+        let submatches: Vec<_> = match line.context == self.invert_match {
+            true => self
+                .spans(line)
+                .into_iter()
+                .map(|(span, _)| Submatch {
+                    text: Data::from(&content[span.clone()]),
+                    start: span.start,
+                    end: span.end,
+                })
+                .collect(),
+            false => Vec::new(),
+        };
+        self.stats.matches += submatches.len() as u64;
+
+        let lines = Lines {
+            path: Data::from(self.path.as_bytes()),
+            lines: Data::from(line.bytes),
+            line_number: line.number,
+            absolute_offset: line.offset,
+            submatches,
+        };
+        let message = match line.context {
+            true => Message::Context(lines),
+            false => {
+                self.stats.matched_lines += 1;
+                Message::Match(lines)
+            }
+        };
+        write_json(&mut self.out, &message)?;
+
+        if self.line_buffered {
+            self.out.flush()?;
+        }
+
+        Ok(())
+    }
+
+    /// Printed instead of the lines of a binary file
+    fn print_binary(&mut self, name: &str) -> io::Result<()> {
+        if self.json {
+            self.binary = true;
+            return self.begin();
+        }
+
+        writeln!(self.out, "Binary file {name} matches")
+    }
+
+    /// Count line of -c
+    fn print_count(&mut self, filename: Option<&str>, count: u64) -> io::Result<()> {
+        if let Some(name) = filename {
+            self.paint(|c| &c.filename, name.as_bytes())?;
+            self.paint(|c| &c.separator, b":")?;
+        }
+        writeln!(self.out, "{count}")
+    }
+}
+
+/// Counts of the --json end and summary messages
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize)]
+struct Stats {
+    searches: u64,
+    searches_with_match: u64,
+    matched_lines: u64,
+    matches: u64,
+}
+
+impl AddAssign for Stats {
+    fn add_assign(&mut self, other: Self) {
+        self.searches += other.searches;
+        self.searches_with_match += other.searches_with_match;
+        self.matched_lines += other.matched_lines;
+        self.matches += other.matches;
+    }
+}
+
+/// A message of the --json output
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", content = "data", rename_all = "lowercase")]
+enum Message<'a> {
+    Begin {
+        path: Data<'a>,
+    },
+    Match(Lines<'a>),
+    Context(Lines<'a>),
+    End {
+        path: Data<'a>,
+        binary: bool,
+        stats: Stats,
+    },
+    Summary {
+        stats: Stats,
+    },
+}
+
+#[derive(Debug, Serialize)]
+struct Lines<'a> {
+    path: Data<'a>,
+    /// The line, terminator included
+    lines: Data<'a>,
+    line_number: u64,
+    absolute_offset: u64,
+    submatches: Vec<Submatch<'a>>,
+}
+
+/// A match in a line, `start` and `end` are byte offsets in the line
+#[derive(Debug, Serialize)]
+struct Submatch<'a> {
+    #[serde(rename = "match")]
+    text: Data<'a>,
+    start: usize,
+    end: usize,
+}
+
+/// Text, or base64 encoded bytes when it is not valid UTF-8
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Data<'a> {
+    Text(&'a str),
+    Bytes(String),
+}
+
+impl<'a> From<&'a [u8]> for Data<'a> {
+    fn from(bytes: &'a [u8]) -> Self {
+        match std::str::from_utf8(bytes) {
+            Ok(text) => Self::Text(text),
+            Err(_) => Self::Bytes(BASE64.encode(bytes)),
+        }
+    }
+}
+
+fn write_json(out: &mut impl Write, message: &Message) -> io::Result<()> {
+    serde_json::to_writer(&mut *out, message)?;
+    out.write_all(b"\n")
+}
+
+/// The patterns of the operand, of -e and of every -f file. As in GNU
+/// grep, a pattern that contains newlines is a list of patterns
+fn patterns(args: &Args) -> Result<Vec<Vec<u8>>> {
+    let mut patterns: Vec<Vec<u8>> = args
+        .pattern
+        .iter()
+        .chain(&args.regexp)
+        .flat_map(|p| p.split('\n'))
+        .map(|p| p.as_bytes().to_vec())
+        .collect();
+
+    for filename in &args.file {
+        let io_err = |e| Error::io("grep", filename, e);
+        let mut content = LineReader::new(open_file(filename, false).map_err(io_err)?);
+        while let Some(line) = content.next_line().map_err(io_err)? {
+            patterns.push(chomp(line, b'\n').to_vec());
+        }
+    }
+
+    Ok(patterns)
+}
+
+/// Which files `find_files` yields
+#[derive(Debug, Default)]
+struct Walk {
+    recursive: bool,
+    /// Skip what .gitignore and .ignore files list, and .git directories
+    ignore_files: bool,
+    include: GlobSet,
+    exclude: GlobSet,
+    exclude_dir: GlobSet,
+}
+
+impl Walk {
+    fn new(args: &Args) -> Result<Self> {
+        Ok(Self {
+            recursive: args.recursive,
+            ignore_files: !args.no_ignore,
+            include: globs(&args.include)?,
+            exclude: globs(&args.exclude)?,
+            exclude_dir: globs(&args.exclude_dir)?,
+        })
+    }
+
+    /// Whether --include and --exclude let the file at `path` through
+    fn selects(&self, path: &Path) -> bool {
+        let Some(name) = path.file_name() else {
+            return true;
+        };
+
+        !self.exclude.is_match(name) && (self.include.is_empty() || self.include.is_match(name))
+    }
+
+    /// The files under `root`, sorted by name so that the output does not
+    /// depend on the order of the directory entries
+    fn files<'a>(&'a self, root: &'a str) -> impl Iterator<Item = Result<String>> + Send + 'a {
+        let ignore_files = self.ignore_files;
+        let exclude_dir = self.exclude_dir.clone();
+        WalkBuilder::new(root)
+            .standard_filters(false)
+            .ignore(ignore_files)
+            .git_ignore(ignore_files)
+            .git_exclude(ignore_files)
+            .parents(ignore_files)
+            // Ignore files apply outside of git repositories too
+            .require_git(false)
+            .sort_by_file_name(|a, b| a.cmp(b))
+            .filter_entry(move |entry| {
+                let name = entry.file_name();
+                let dir = entry.depth() > 0 && entry.file_type().is_some_and(|t| t.is_dir());
+                !(dir && (exclude_dir.is_match(name) || ignore_files && name == ".git"))
+            })
+            .build()
+            .filter_map(move |entry| match entry {
+                Ok(entry) => {
+                    let file = entry.file_type().is_some_and(|t| t.is_file());
+                    (file && self.selects(entry.path()))
+                        .then(|| Ok(entry.path().display().to_string()))
+                }
+                Err(e) => Some(Err(walk_error(root, e))),
+            })
+    }
+}
+
+fn globs(globs: &[String]) -> Result<GlobSet> {
+    let mut set = GlobSetBuilder::new();
+    for glob in globs {
+        set.add(Glob::new(glob).map_err(|e| Error::usage("grep", e.to_string()))?);
+    }
+
+    set.build().map_err(|e| Error::usage("grep", e.to_string()))
+}
+
+fn walk_error(root: &str, e: ignore::Error) -> Error {
+    fn path(e: &ignore::Error) -> Option<&Path> {
+        match e {
+            ignore::Error::WithPath { path, .. } => Some(path),
+            ignore::Error::WithDepth { err, .. } | ignore::Error::WithLineNumber { err, .. } => {
+                path(err)
+            }
+            _ => None,
+        }
+    }
+
+    let path = path(&e).map_or_else(|| root.to_string(), |p| p.display().to_string());
+    let message = e.to_string();
+    let source = e
+        .into_io_error()
+        .unwrap_or_else(|| io::Error::other(message));
+    Error::io("grep", path, source)
+}
+
+/// The files to search for the `paths` operands, lazily, so that the
+/// search starts before a recursive walk is over
+fn find_files<'a>(
+    paths: &'a [String],
+    walk: &'a Walk,
+) -> impl Iterator<Item = Result<String>> + Send + 'a {
+    type Files<'a> = Box<dyn Iterator<Item = Result<String>> + Send + 'a>;
+
+    paths.iter().flat_map(move |path| -> Files<'a> {
+        if path == "-" {
+            return Box::new(iter::once(Ok(path.to_string())));
+        }
+
+        match fs::metadata(path) {
+            Ok(meta) if meta.is_dir() && walk.recursive => Box::new(walk.files(path)),
+            Ok(meta) if meta.is_dir() => Box::new(iter::once(Err(Error::io(
+                "grep",
+                path,
+                io::Error::new(io::ErrorKind::IsADirectory, "Is a directory"),
+            )))),
+            // FIFOs, such as <(cmd), and devices are read like files
+            Ok(_) if walk.selects(Path::new(path)) => Box::new(iter::once(Ok(path.to_string()))),
+            Ok(_) => Box::new(iter::empty()),
+            Err(e) => Box::new(iter::once(Err(Error::io("grep", path, e)))),
+        }
+    })
+}
+
+pub fn get_args<I, T>(args: I) -> Result<Args>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    let mut args = Args::try_parse_from(args).map_err(|e| Error::args("grep", e))?;
+
+    // With -e or -f, the first operand is a file, not the pattern
+    if !args.regexp.is_empty() || !args.file.is_empty() {
+        if let Some(file) = args.pattern.take() {
+            args.files.insert(0, file);
+        }
+    }
+
+    // As in GNU grep, -r searches the working directory by default
+    if args.files.is_empty() {
+        let default = if args.recursive { "." } else { "-" };
+        args.files.push(default.to_string());
+    }
+
+    Ok(args)
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::{
+        find_files, Colors, Data, Line, MatchOptions, Matcher, Message, Searcher, Stats, Walk,
+    };
+    use globset::{Glob, GlobSetBuilder};
+    use rand::{distributions::Alphanumeric, Rng};
+
+    #[test]
+    fn test_searcher() {
+        let input = "an error\nall good\nlast error";
+        let searcher = Searcher::new(regex::bytes::Regex::new("error").unwrap());
+        let expected = vec![
+            Line {
+                number: 1,
+                offset: 0,
+                bytes: b"an error\n",
+                context: false,
+                matches: None,
+            },
+            Line {
+                number: 3,
+                offset: 18,
+                bytes: b"last error",
+                context: false,
+                matches: None,
+            },
+        ];
+
+        let mut lines = Vec::new();
+        searcher
+            .search(input.as_bytes(), |line| {
+                lines.push((line.number, line.offset, line.bytes.to_vec()));
+                Ok(true)
+            })
+            .unwrap();
+        let owned = |lines: &[Line]| {
+            lines
+                .iter()
+                .map(|l| (l.number, l.offset, l.bytes.to_vec()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(lines, owned(&expected));
+
+        let mut lines = Vec::new();
+        searcher
+            .search_in(input.as_bytes(), |line| {
+                lines.push((line.number, line.offset, line.bytes.to_vec()));
+                Ok(false)
+            })
+            .unwrap();
+        assert_eq!(lines, owned(&expected[..1]));
+
+        let mut lines = Vec::new();
+        searcher
+            .invert_match(true)
+            .search(input.as_bytes(), |line| {
+                lines.push(line.bytes.to_vec());
+                Ok(true)
+            })
+            .unwrap();
+        assert_eq!(lines, vec![b"all good\n".to_vec()]);
+
+        let mut lines = Vec::new();
+        Searcher::new(regex::bytes::Regex::new("3|7").unwrap())
+            .before_context(1)
+            .after_context(1)
+            .search_in(b"1\n2\n3\n4\n5\n6\n7\n8\n9\n", |line| {
+                lines.push((line.number, line.context));
+                Ok(true)
+            })
+            .unwrap();
+        assert_eq!(
+            lines,
+            vec![
+                (2, true),
+                (3, false),
+                (4, true),
+                (6, true),
+                (7, false),
+                (8, true)
+            ]
+        );
+        let mut lines = Vec::new();
+        Searcher::new(regex::bytes::Regex::new("a").unwrap())
+            .max_count(Some(2))
+            .search(&b"a\nb\na\na\n"[..], |line| {
+                lines.push(line.number);
+                Ok(true)
+            })
+            .unwrap();
+        assert_eq!(lines, vec![1, 3]);
+
+        let input = b"at main\nError: boom\n  at run\n  at main\nok\n";
+        let searcher = Searcher::new(regex::bytes::Regex::new(r"(?s)Error.*?main").unwrap());
+        let mut lines = Vec::new();
+        searcher
+            .clone()
+            .multiline(true)
+            .search(&input[..], |line| {
+                lines.push(line.number);
+                Ok(true)
+            })
+            .unwrap();
+        assert_eq!(lines, vec![2, 3, 4]);
+
+        let mut lines = Vec::new();
+        searcher
+            .multiline(true)
+            .invert_match(true)
+            .search_in(input, |line| {
+                lines.push(line.number);
+                Ok(true)
+            })
+            .unwrap();
+        assert_eq!(lines, vec![1, 5]);
+
+        // A match that ends with a newline does not select the next line,
+        // and empty matches select their line
+        let mut lines = Vec::new();
+        Searcher::new(regex::bytes::Regex::new(r"(?m)b\n|^$").unwrap())
+            .multiline(true)
+            .search_in(b"a\nb\nc\n\nd\n", |line| {
+                lines.push(line.number);
+                Ok(true)
+            })
+            .unwrap();
+        assert_eq!(lines, vec![2, 4]);
+    }
+
+    #[test]
+    fn test_matcher() {
+        let patterns = [b"a.c".to_vec(), b"xyz".to_vec()];
+        let fixed = MatchOptions {
+            fixed_strings: true,
+            ..Default::default()
+        };
+        let matcher = Matcher::new(&patterns, fixed).unwrap();
+        assert!(matches!(matcher, Matcher::Literals(_)));
+        assert!(matcher.is_match(b"1 a.c 2"));
+        assert!(!matcher.is_match(b"abc"));
+
+        let ignore_case = MatchOptions {
+            ignore_case: true,
+            ..Default::default()
+        };
+        let matcher = Matcher::new(&patterns, ignore_case).unwrap();
+        assert!(matches!(matcher, Matcher::Regex(_)));
+        assert!(matcher.is_match(b"ABC"));
+        assert!(matcher.is_match(b"XYZ"));
+
+        let regex = MatchOptions::default();
+        assert!(!Matcher::new(&[], regex).unwrap().is_match(b"abc"));
+        assert!(Matcher::new(&[b"(".to_vec()], regex).is_err());
+
+        let matcher = Matcher::new(&[b"ab".to_vec(), b"abcd".to_vec()], fixed).unwrap();
+        assert_eq!(
+            matcher.find_iter(b"xabcdab").collect::<Vec<_>>(),
+            vec![1..5, 5..7]
+        );
+        let matcher = Matcher::new(&[b"x*".to_vec()], regex).unwrap();
+        assert_eq!(
+            matcher.find_iter(b"axxbx").collect::<Vec<_>>(),
+            vec![1..3, 4..5]
+        );
+
+        let word = MatchOptions {
+            fixed_strings: true,
+            word_regexp: true,
+            ..Default::default()
+        };
+        let matcher = Matcher::new(&[b"foo".to_vec(), b"-x".to_vec()], word).unwrap();
+        assert_eq!(
+            matcher.find_iter(b"a foo_x foo -x").collect::<Vec<_>>(),
+            vec![8..11, 12..14]
+        );
+        assert!(!matcher.is_match(b"foobar"));
+
+        let line = MatchOptions {
+            line_regexp: true,
+            ..Default::default()
+        };
+        let matcher = Matcher::new(&[b"fo+".to_vec(), b"bar".to_vec()], line).unwrap();
+        assert!(matcher.is_match(b"foo"));
+        assert!(matcher.is_match(b"bar"));
+        assert!(!matcher.is_match(b"foo bar"));
+
+        let captures = MatchOptions {
+            fixed_strings: true,
+            captures: true,
+            ..Default::default()
+        };
+        let matcher = Matcher::new(&[b"a.c".to_vec()], captures).unwrap();
+        assert!(matches!(matcher, Matcher::Regex(_)));
+        let mut dst = Vec::new();
+        matcher.expand(b"xa.c", 1..4, b"[$0]", &mut dst);
+        assert_eq!(dst, b"[a.c]");
+
+        let matcher: Matcher = regex::bytes::Regex::new(r"(\w+)=(?P<value>\w+)")
+            .unwrap()
+            .into();
+        let mut dst = Vec::new();
+        matcher.expand(b"a=1 b=2", 4..7, b"${value}=$1", &mut dst);
+        assert_eq!(dst, b"2=b");
+    }
+
+    #[test]
+    fn test_json() {
+        assert_eq!(Data::from(&b"abc"[..]), Data::Text("abc"));
+        assert_eq!(Data::from(&b"\xff"[..]), Data::Bytes("/w==".to_string()));
+
+        let message = Message::Begin {
+            path: Data::from(&b"a.txt"[..]),
+        };
+        assert_eq!(
+            serde_json::to_string(&message).unwrap(),
+            r#"{"type":"begin","data":{"path":{"text":"a.txt"}}}"#
+        );
+
+        let mut stats = Stats {
+            searches: 1,
+            matches: 2,
+            ..Default::default()
+        };
+        stats += stats;
+        assert_eq!(stats.searches, 2);
+        assert_eq!(stats.matches, 4);
+    }
+
+    #[test]
+    fn test_colors() {
+        assert_eq!(Colors::parse(""), Colors::default());
+
+        let colors = Colors::parse("mt=04:fn=:ne:xx=1");
+        assert_eq!(colors.selected_match, "04");
+        assert_eq!(colors.context_match, "04");
+        assert_eq!(colors.filename, "");
+        assert_eq!(colors.line_number, "32");
+        assert!(colors.no_erase);
+        assert!(!colors.reverse);
+    }
+
+    #[test]
+    fn test_find_files() {
+        let walk = Walk::default();
+
+        // Simple file
+        let paths = ["./tests/inputs/cat_empty.txt".to_string()];
+        let files: Vec<_> = find_files(&paths, &walk).collect();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].as_ref().unwrap(), "./tests/inputs/cat_empty.txt");
+
+        // Fail when directory and not recursive
+        let paths = ["./tests/inputs".to_string()];
+        let files: Vec<_> = find_files(&paths, &walk).collect();
+        assert_eq!(files.len(), 1);
+        if let Err(e) = &files[0] {
+            assert_eq!(e.to_string(), "grep: ./tests/inputs: Is a directory");
+        }
+
+        // Non existent
+        let bad: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(10)
+            .map(char::from)
+            .collect();
+
+        let paths = [bad];
+        let files: Vec<_> = find_files(&paths, &walk).collect();
+        assert_eq!(files.len(), 1);
+        assert!(files[0].is_err());
+
+        // Recursive, sorted by name, and filtered by --include
+        let mut walk = Walk {
+            recursive: true,
+            ..Default::default()
+        };
+        let paths = ["tests/inputs/grep".to_string()];
+        let files: Vec<_> = find_files(&paths, &walk).map(Result::unwrap).collect();
+        assert_eq!(
+            files,
+            ["tests/inputs/grep/a.txt", "tests/inputs/grep/b.txt"]
+        );
+
+        walk.include = GlobSetBuilder::new()
+            .add(Glob::new("b*").unwrap())
+            .build()
+            .unwrap();
+        let files: Vec<_> = find_files(&paths, &walk).map(Result::unwrap).collect();
+        assert_eq!(files, ["tests/inputs/grep/b.txt"]);
+    }
+}
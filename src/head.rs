@@ -0,0 +1,120 @@
+use std::ffi::OsString;
+use std::io::{Read, Write};
+
+use crate::{open_file, Decompress, Error, LineReader, Output, Result, Terminator};
+use clap::Parser;
+
+#[derive(Parser, Debug)]
+#[command(name = "head", author = "protium", version = "0.1.0", about = "")]
+pub struct Args {
+    #[arg(
+        name = "files",
+        value_name = "FILES",
+        default_value = "-",
+        help = "Input file(s)"
+    )]
+    pub files: Vec<String>,
+
+    #[arg(
+        name = "lines",
+        value_name = "LINES",
+        short = 'n',
+        long = "lines",
+        help = "Number of lines",
+        default_value_t = 10,
+        conflicts_with = "bytes"
+    )]
+    pub lines: usize,
+
+    #[arg(
+        name = "bytes",
+        value_name = "BYTES",
+        short = 'c',
+        long = "bytes",
+        help = "Number of bytes"
+    )]
+    pub bytes: Option<usize>,
+
+    #[command(flatten)]
+    pub decompress: Decompress,
+
+    #[command(flatten)]
+    pub terminator: Terminator,
+}
+
+pub fn run(args: Args) -> Result<()> {
+    let print_header = args.files.len() > 1;
+    let mut out = Output::new();
+    let mut failed = false;
+    for (i, f) in args.files.iter().enumerate() {
+        if let Err(e) = head(f, i > 0, print_header, &args, &mut out) {
+            eprintln!("{e}");
+            failed = true;
+        }
+    }
+
+    out.flush()
+        .map_err(|e| Error::io("head", "standard output", e))?;
+
+    if failed {
+        return Err(Error::Status(1));
+    }
+
+    Ok(())
+}
+
+fn head(
+    filename: &str,
+    separate: bool,
+    print_header: bool,
+    args: &Args,
+    out: &mut Output,
+) -> Result<()> {
+    let io_err = |e| Error::io("head", filename, e);
+    let out_err = |e| Error::io("head", "standard output", e);
+    let content = open_file(filename, args.decompress.enabled()).map_err(io_err)?;
+    if separate {
+        writeln!(out).map_err(out_err)?;
+    }
+
+    if print_header {
+        writeln!(
+            out,
+            "==> {} <==",
+            if filename == "-" {
+                "standard input"
+            } else {
+                filename
+            }
+        )
+        .map_err(out_err)?;
+    }
+
+    if let Some(count) = args.bytes {
+        let mut buf: Vec<u8> = Vec::with_capacity(count);
+        content
+            .take(count as u64)
+            .read_to_end(&mut buf)
+            .map_err(io_err)?;
+        out.write_all(&buf).map_err(out_err)?;
+        return Ok(());
+    }
+
+    let mut content = LineReader::with_delimiter(content, args.terminator.byte());
+    for _ in 0..args.lines {
+        match content.next_line().map_err(io_err)? {
+            Some(line) => out.write_all(line).map_err(out_err)?,
+            None => break,
+        }
+    }
+
+    Ok(())
+}
+
+pub fn get_args<I, T>(args: I) -> Result<Args>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    Args::try_parse_from(args).map_err(|e| Error::args("head", e))
+}
@@ -1,19 +1,459 @@
+use std::fmt;
 use std::fs::File;
-use std::io::{self, BufRead, BufReader};
+use std::io::{self, BufRead, BufReader, BufWriter, IsTerminal, Read, Write};
 
-pub type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+use flate2::bufread::MultiGzDecoder;
+use memmap2::Mmap;
+use xz2::bufread::XzDecoder;
 
-/// Opens a file and returns a BufRead
-/// Treats '-' as stdin
-pub fn open_file(filename: &str) -> Result<Box<dyn BufRead>> {
-    match filename {
-        "-" => Ok(Box::new(BufReader::new(io::stdin()))),
-        _ => {
-            let file = File::open(filename);
-            match file {
-                Ok(file) => Ok(Box::new(BufReader::new(file))),
-                Err(e) => Err(e.into()),
+pub mod cat;
+pub mod comm;
+pub mod cut;
+pub mod echo;
+pub mod find;
+pub mod grep;
+pub mod head;
+pub mod pipe;
+pub mod tail;
+pub mod uniq;
+pub mod wc;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Errors reported by the tools. Rendered as "tool: path: message"
+#[derive(Debug)]
+pub enum Error {
+    /// Reading or writing `path` failed
+    Io {
+        tool: &'static str,
+        path: String,
+        source: io::Error,
+    },
+    /// Invalid arguments or input that clap cannot validate
+    Usage { tool: &'static str, message: String },
+    /// Command line parsing failed, or help or version was requested
+    Args {
+        tool: &'static str,
+        source: clap::Error,
+    },
+    /// The failure was already reported, only the exit status is left
+    Status(i32),
+}
+
+impl Error {
+    pub fn io(tool: &'static str, path: impl Into<String>, source: io::Error) -> Self {
+        Self::Io {
+            tool,
+            path: path.into(),
+            source,
+        }
+    }
+
+    pub fn usage(tool: &'static str, message: impl Into<String>) -> Self {
+        Self::Usage {
+            tool,
+            message: message.into(),
+        }
+    }
+
+    pub fn args(tool: &'static str, source: clap::Error) -> Self {
+        Self::Args { tool, source }
+    }
+
+    /// The kind of the underlying io error, if any
+    pub fn kind(&self) -> Option<io::ErrorKind> {
+        match self {
+            Self::Io { source, .. } => Some(source.kind()),
+            _ => None,
+        }
+    }
+
+    /// GNU compatible exit status: 1 on failure, except for grep that
+    /// reserves 1 for "no lines selected" and uses 2 for errors
+    pub fn exit_code(&self) -> i32 {
+        let failure = |tool: &str| if tool == "grep" { 2 } else { 1 };
+        match self {
+            Self::Io { tool, .. } | Self::Usage { tool, .. } => failure(tool),
+            Self::Args { tool, source } => {
+                if source.use_stderr() {
+                    failure(tool)
+                } else {
+                    0
+                }
             }
+            Self::Status(code) => *code,
         }
     }
+
+    /// Reports the error on stderr (help and version go to stdout) and
+    /// exits with `exit_code`
+    pub fn exit(self) -> ! {
+        self.report();
+        std::process::exit(self.exit_code())
+    }
+
+    /// Reports the error like `exit` does, without exiting
+    pub fn report(&self) {
+        match self {
+            Self::Args { source, .. } => {
+                let _ = source.print();
+            }
+            Self::Status(_) => (),
+            _ => eprintln!("{self}"),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io { tool, path, source } => {
+                write!(f, "{tool}: {path}: {}", describe(source))
+            }
+            Self::Usage { tool, message } => write!(f, "{tool}: {message}"),
+            Self::Args { source, .. } => write!(f, "{}", source.to_string().trim_end()),
+            Self::Status(code) => write!(f, "exit status {code}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io { source, .. } => Some(source),
+            Self::Args { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// Formats an io error like the C library does, without the
+/// " (os error N)" suffix that std appends
+fn describe(e: &io::Error) -> String {
+    let msg = e.to_string();
+    match (e.raw_os_error(), msg.rfind(" (os error ")) {
+        (Some(_), Some(i)) => msg[..i].to_string(),
+        _ => msg,
+    }
+}
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const XZ_MAGIC: &[u8] = &[0xfd, b'7', b'z', b'X', b'Z', 0x00];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+/// Opens a file and returns a BufRead
+/// Treats '-' as stdin, or as the previous stage inside a pipeline. With
+/// `decompress`, gzip, xz and zstd input is detected by its magic bytes
+/// and decompressed on the fly
+pub fn open_file(filename: &str, decompress: bool) -> io::Result<Box<dyn BufRead>> {
+    let reader: Box<dyn BufRead> = match filename {
+        "-" => pipe::stdin().unwrap_or_else(|| Box::new(BufReader::new(io::stdin()))),
+        _ => Box::new(BufReader::new(File::open(filename)?)),
+    };
+
+    maybe_decompressed(reader, decompress)
+}
+
+fn maybe_decompressed(reader: Box<dyn BufRead>, decompress: bool) -> io::Result<Box<dyn BufRead>> {
+    if decompress {
+        decompressed(reader)
+    } else {
+        Ok(reader)
+    }
+}
+
+fn decompressed(mut reader: Box<dyn BufRead>) -> io::Result<Box<dyn BufRead>> {
+    // A pipe may return less than the longest magic on the first read
+    let mut magic = Vec::with_capacity(XZ_MAGIC.len());
+    while magic.len() < XZ_MAGIC.len() {
+        let buf = reader.fill_buf()?;
+        if buf.is_empty() {
+            break;
+        }
+
+        let n = buf.len().min(XZ_MAGIC.len() - magic.len());
+        magic.extend_from_slice(&buf[..n]);
+        reader.consume(n);
+    }
+    let (gzip, xz, zstd) = (
+        magic.starts_with(GZIP_MAGIC),
+        magic.starts_with(XZ_MAGIC),
+        magic.starts_with(ZSTD_MAGIC),
+    );
+    let reader: Box<dyn BufRead> = Box::new(io::Cursor::new(magic).chain(reader));
+
+    Ok(if gzip {
+        Box::new(BufReader::new(MultiGzDecoder::new(reader)))
+    } else if xz {
+        Box::new(BufReader::new(XzDecoder::new_multi_decoder(reader)))
+    } else if zstd {
+        Box::new(BufReader::new(zstd::Decoder::with_buffer(reader)?))
+    } else {
+        reader
+    })
+}
+
+/// An open file, mapped into memory when it can be
+pub enum MappedFile {
+    Mapped(Mmap),
+    /// Pipes, directories and other special files, and empty files, which
+    /// callers then read through a buffer as usual
+    File(File),
+}
+
+/// Maps `file` into memory if it is a regular file. Otherwise hands the
+/// same descriptor back, so that a FIFO is never opened twice
+pub fn map_file(file: File) -> io::Result<MappedFile> {
+    let metadata = file.metadata()?;
+    if !metadata.is_file() || metadata.len() == 0 {
+        return Ok(MappedFile::File(file));
+    }
+
+    // SAFETY: the mapping is read only, but not a snapshot: if another
+    // process truncates the file while a tool reads it, touching the pages
+    // past the new end raises SIGBUS and kills the process. The tools
+    // accept that for regular files, as they never write to their inputs
+    unsafe { Mmap::map(&file) }.map(MappedFile::Mapped)
+}
+
+/// Input of the tools that can work on a whole file at once
+pub enum Input {
+    /// A regular, uncompressed file mapped into memory
+    Mapped(Mmap),
+    /// Anything else, read through a buffer
+    Stream(Box<dyn BufRead>),
+}
+
+/// Like `open_file`, but maps regular files into memory. Compressed files
+/// are decompressed from the mapping
+pub fn open_input(filename: &str, decompress: bool) -> io::Result<Input> {
+    if filename == "-" {
+        return open_file(filename, decompress).map(Input::Stream);
+    }
+
+    match map_file(File::open(filename)?)? {
+        MappedFile::Mapped(map) if decompress && is_compressed(&map) => {
+            decompressed(Box::new(io::Cursor::new(map))).map(Input::Stream)
+        }
+        MappedFile::Mapped(map) => Ok(Input::Mapped(map)),
+        MappedFile::File(file) => {
+            maybe_decompressed(Box::new(BufReader::new(file)), decompress).map(Input::Stream)
+        }
+    }
+}
+
+fn is_compressed(content: &[u8]) -> bool {
+    [GZIP_MAGIC, XZ_MAGIC, ZSTD_MAGIC]
+        .iter()
+        .any(|magic| content.starts_with(magic))
+}
+
+const OUTPUT_BUFFER_SIZE: usize = 64 * 1024;
+
+/// Locked, block buffered standard output shared by the tools. A closed
+/// pipe (EPIPE), as in `cat big.log | head`, is not an error: the process
+/// exits quietly with status 0. Inside a pipeline the output goes to the
+/// next stage instead
+pub struct Output(BufWriter<Box<dyn Write>>);
+
+impl Output {
+    pub fn new() -> Self {
+        let sink = pipe::stdout().unwrap_or_else(|| Box::new(io::stdout().lock()));
+        Self(BufWriter::with_capacity(OUTPUT_BUFFER_SIZE, sink))
+    }
+}
+
+/// Whether `Output` writes to a terminal. The output of a pipeline stage
+/// goes to the next stage, even when the process runs in a terminal
+pub fn stdout_is_terminal() -> bool {
+    !pipe::is_stage() && io::stdout().is_terminal()
+}
+
+impl Default for Output {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn exit_on_broken_pipe<T>(result: io::Result<T>) -> io::Result<T> {
+    match result {
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => pipe::exit_stage(),
+        result => result,
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        exit_on_broken_pipe(self.0.write(buf))
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        exit_on_broken_pipe(self.0.write_all(buf))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        exit_on_broken_pipe(self.0.flush())
+    }
+}
+
+/// Decompression flag shared by the tools that read compressed input
+#[derive(clap::Args, Debug, Clone, Copy, Default)]
+pub struct Decompress {
+    #[arg(
+        name = "no-decompress",
+        long = "no-decompress",
+        help = "Read gzip, xz and zstd input as is instead of decompressing it"
+    )]
+    pub no_decompress: bool,
+}
+
+impl Decompress {
+    /// Whether compressed input is detected and decompressed
+    pub fn enabled(self) -> bool {
+        !self.no_decompress
+    }
+}
+
+/// Record terminator flag shared by the line oriented tools
+#[derive(clap::Args, Debug, Clone, Copy, Default)]
+pub struct Terminator {
+    #[arg(
+        name = "zero-terminated",
+        short = 'z',
+        long = "zero-terminated",
+        visible_alias = "null-data",
+        help = "Line delimiter is NUL, not newline"
+    )]
+    pub zero: bool,
+}
+
+impl Terminator {
+    /// The byte that ends every input and output line
+    pub fn byte(self) -> u8 {
+        if self.zero {
+            b'\0'
+        } else {
+            b'\n'
+        }
+    }
+}
+
+/// Reads lines as raw bytes, reusing a single buffer, so invalid UTF-8
+/// is passed through untouched. Lines end with '\n' unless another
+/// delimiter is given
+pub struct LineReader<R> {
+    reader: R,
+    buf: Vec<u8>,
+    delim: u8,
+}
+
+impl<R: BufRead> LineReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self::with_delimiter(reader, b'\n')
+    }
+
+    pub fn with_delimiter(reader: R, delim: u8) -> Self {
+        Self {
+            reader,
+            buf: Vec::new(),
+            delim,
+        }
+    }
+
+    /// Returns the next line including its terminator, or `None` at EOF.
+    /// The last line may not have a terminator
+    pub fn next_line(&mut self) -> io::Result<Option<&[u8]>> {
+        self.buf.clear();
+        if self.reader.read_until(self.delim, &mut self.buf)? == 0 {
+            return Ok(None);
+        }
+
+        Ok(Some(&self.buf))
+    }
+
+    pub fn delimiter(&self) -> u8 {
+        self.delim
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+/// Strips the trailing line terminator `delim`, if any
+pub fn chomp(line: &[u8], delim: u8) -> &[u8] {
+    line.strip_suffix(&[delim]).unwrap_or(line)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::io::Read;
+
+    #[test]
+    fn test_error_display() {
+        let e = Error::io("cat", "missing.txt", io::Error::from_raw_os_error(2));
+        assert_eq!(e.to_string(), "cat: missing.txt: No such file or directory");
+        assert_eq!(e.kind(), Some(io::ErrorKind::NotFound));
+        assert_eq!(e.exit_code(), 1);
+
+        let e = Error::usage("grep", "invalid pattern");
+        assert_eq!(e.to_string(), "grep: invalid pattern");
+        assert_eq!(e.kind(), None);
+        assert_eq!(e.exit_code(), 2);
+
+        assert_eq!(Error::Status(1).exit_code(), 1);
+    }
+
+    #[test]
+    fn test_line_reader() {
+        let mut reader = LineReader::new(io::Cursor::new(b"caf\xe9\n\xff\xfe\nend".to_vec()));
+        assert_eq!(reader.next_line().unwrap(), Some(&b"caf\xe9\n"[..]));
+        assert_eq!(reader.next_line().unwrap(), Some(&b"\xff\xfe\n"[..]));
+        assert_eq!(reader.next_line().unwrap(), Some(&b"end"[..]));
+        assert_eq!(reader.next_line().unwrap(), None);
+
+        assert_eq!(chomp(b"line\n", b'\n'), b"line");
+        assert_eq!(chomp(b"line", b'\n'), b"line");
+
+        let mut reader = LineReader::with_delimiter(io::Cursor::new(b"a\nb\0c\0".to_vec()), b'\0');
+        assert_eq!(reader.next_line().unwrap(), Some(&b"a\nb\0"[..]));
+        assert_eq!(reader.next_line().unwrap(), Some(&b"c\0"[..]));
+        assert_eq!(reader.next_line().unwrap(), None);
+        assert_eq!(chomp(b"a\nb\0", b'\0'), b"a\nb");
+    }
+
+    #[test]
+    fn test_open_input() {
+        let input = open_input("tests/inputs/compressed.txt", true).unwrap();
+        assert!(matches!(input, Input::Mapped(_)));
+
+        let input = open_input("tests/inputs/compressed.txt.gz", true).unwrap();
+        let Input::Stream(mut content) = input else {
+            panic!("compressed input is not mapped");
+        };
+        let mut text = String::new();
+        content.read_to_string(&mut text).unwrap();
+        assert_eq!(
+            text,
+            fs::read_to_string("tests/inputs/compressed.txt").unwrap()
+        );
+
+        let input = open_input("tests/inputs/compressed.txt.gz", false).unwrap();
+        assert!(matches!(input, Input::Mapped(_)));
+        let dir = File::open("tests/inputs").unwrap();
+        assert!(matches!(map_file(dir).unwrap(), MappedFile::File(_)));
+
+        // The magic is found across short reads, as from a pipe
+        let compressed = fs::read("tests/inputs/compressed.txt.xz").unwrap();
+        let reader = BufReader::with_capacity(1, io::Cursor::new(compressed));
+        let mut content = decompressed(Box::new(reader)).unwrap();
+        let mut text = String::new();
+        content.read_to_string(&mut text).unwrap();
+        assert_eq!(
+            text,
+            fs::read_to_string("tests/inputs/compressed.txt").unwrap()
+        );
+    }
 }
@@ -1,3 +1,301 @@
+use std::{
+    env,
+    ffi::{OsStr, OsString},
+    fs::File,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use clap::{Command, CommandFactory, Parser, Subcommand};
+use clap_complete::Shell;
+use clap_mangen::Man;
+use runix::{
+    cat, comm, cut, echo, find, grep, head,
+    pipe::{self, Tool},
+    tail, uniq, wc, Error, Output, Result,
+};
+
+type Definition = fn() -> Command;
+
+/// Name, entry point and clap definition of every tool
+const TOOLS: &[(&str, Tool, Definition)] = &[
+    (
+        "cat",
+        |args| cat::get_args(args).and_then(cat::run),
+        cat::Args::command,
+    ),
+    (
+        "comm",
+        |args| comm::get_args(args).and_then(comm::run),
+        comm::Args::command,
+    ),
+    (
+        "cut",
+        |args| cut::get_args(args).and_then(cut::run),
+        cut::Args::command,
+    ),
+    (
+        "echo",
+        |args| echo::get_args(args).and_then(echo::run),
+        echo::command,
+    ),
+    (
+        "false",
+        |_| Err(Error::Status(1)),
+        || Command::new("false").about("do nothing, unsuccessfully"),
+    ),
+    (
+        "find",
+        |args| find::get_args(args).and_then(find::run),
+        find::Args::command,
+    ),
+    (
+        "grep",
+        |args| grep::get_args(args).and_then(grep::run),
+        grep::Args::command,
+    ),
+    (
+        "head",
+        |args| head::get_args(args).and_then(head::run),
+        head::Args::command,
+    ),
+    (
+        "tail",
+        |args| tail::get_args(args).and_then(tail::run),
+        tail::Args::command,
+    ),
+    (
+        "true",
+        |_| Ok(()),
+        || Command::new("true").about("do nothing, successfully"),
+    ),
+    (
+        "uniq",
+        |args| uniq::get_args(args).and_then(uniq::run),
+        uniq::Args::command,
+    ),
+    (
+        "wc",
+        |args| wc::get_args(args).and_then(wc::run),
+        wc::Args::command,
+    ),
+];
+
+#[derive(Parser, Debug)]
+#[command(
+    name = "runix",
+    author = "protium",
+    version = "0.1.0",
+    about = "Multicall binary for the runix utilities. Run as `runix TOOL [ARGS]` or through a symlink named after the tool"
+)]
+struct Args {
+    #[arg(
+        name = "list",
+        short = 'l',
+        long = "list",
+        help = "List the available tools",
+        conflicts_with = "install"
+    )]
+    list: bool,
+
+    #[arg(
+        name = "install",
+        value_name = "DIR",
+        long = "install",
+        help = "Create a symlink to this binary in DIR for every tool"
+    )]
+    install: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: Option<Action>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Action {
+    /// Print shell completions for the tools
+    Completions {
+        #[arg(value_name = "SHELL", help = "Target shell")]
+        shell: Shell,
+
+        #[arg(
+            value_name = "DIR",
+            long = "dir",
+            help = "Write one file per tool to DIR instead of standard output"
+        )]
+        dir: Option<PathBuf>,
+
+        #[arg(value_name = "TOOLS", help = "Tools to generate for, all by default")]
+        tools: Vec<String>,
+    },
+
+    /// Run a pipeline of tools, e.g. 'grep x | cut -f1 | uniq -c', in this process
+    Pipe {
+        #[arg(
+            value_name = "PIPELINE",
+            help = "Tools separated by |, quoted as in sh"
+        )]
+        pipeline: String,
+    },
+
+    /// Print roff man pages for the tools
+    Man {
+        #[arg(
+            value_name = "DIR",
+            long = "dir",
+            help = "Write TOOL.1 files to DIR instead of standard output"
+        )]
+        dir: Option<PathBuf>,
+
+        #[arg(value_name = "TOOLS", help = "Tools to generate for, all by default")]
+        tools: Vec<String>,
+    },
+}
+
+fn find_tool(name: &OsStr) -> Option<Tool> {
+    TOOLS
+        .iter()
+        .find(|(tool, ..)| name == *tool)
+        .map(|(_, run, _)| *run)
+}
+
+fn unknown_tool(name: &str) -> Error {
+    Error::usage(
+        "runix",
+        format!("unknown tool '{name}', see `runix --list`"),
+    )
+}
+
+/// The clap commands of `names`, or of every tool when `names` is empty
+fn commands(names: &[String]) -> Result<Vec<(&'static str, Command)>> {
+    if names.is_empty() {
+        return Ok(TOOLS.iter().map(|(name, _, cmd)| (*name, cmd())).collect());
+    }
+
+    names
+        .iter()
+        .map(|name| {
+            TOOLS
+                .iter()
+                .find(|(tool, ..)| name == tool)
+                .map(|(tool, _, cmd)| (*tool, cmd()))
+                .ok_or_else(|| unknown_tool(name))
+        })
+        .collect()
+}
+
+fn completions(shell: Shell, dir: Option<&Path>, tools: &[String]) -> Result<()> {
+    let mut out = Output::new();
+    for (name, mut cmd) in commands(tools)? {
+        match dir {
+            Some(dir) => {
+                clap_complete::generate_to(shell, &mut cmd, name, dir)
+                    .map_err(|e| Error::io("runix", dir.display().to_string(), e))?;
+            }
+            None => clap_complete::generate(shell, &mut cmd, name, &mut out),
+        }
+    }
+
+    out.flush()
+        .map_err(|e| Error::io("runix", "standard output", e))
+}
+
+fn run_pipeline(pipeline: &str) -> Result<()> {
+    let stages = pipe::parse(pipeline)
+        .map_err(|e| Error::usage("runix", e))?
+        .into_iter()
+        .map(|words| {
+            let tool = find_tool(OsStr::new(&words[0])).ok_or_else(|| unknown_tool(&words[0]))?;
+            Ok((tool, words.into_iter().map(OsString::from).collect()))
+        })
+        .collect::<Result<_>>()?;
+
+    pipe::run(stages)
+}
+
+fn man(dir: Option<&Path>, tools: &[String]) -> Result<()> {
+    let mut out = Output::new();
+    for (name, cmd) in commands(tools)? {
+        match dir {
+            Some(dir) => {
+                let path = dir.join(format!("{name}.1"));
+                let path_err = |e| Error::io("runix", path.display().to_string(), e);
+                let mut file = File::create(&path).map_err(path_err)?;
+                Man::new(cmd).render(&mut file).map_err(path_err)?;
+            }
+            None => Man::new(cmd)
+                .render(&mut out)
+                .map_err(|e: io::Error| Error::io("runix", "standard output", e))?,
+        }
+    }
+
+    out.flush()
+        .map_err(|e| Error::io("runix", "standard output", e))
+}
+
+#[cfg(unix)]
+fn install(dir: &Path) -> Result<()> {
+    let exe = env::current_exe().map_err(|e| Error::io("runix", "current executable", e))?;
+    for (name, ..) in TOOLS {
+        let link = dir.join(name);
+        std::os::unix::fs::symlink(&exe, &link)
+            .map_err(|e| Error::io("runix", link.display().to_string(), e))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn install(_: &Path) -> Result<()> {
+    Err(Error::usage("runix", "--install is only supported on unix"))
+}
+
+fn run(args: Args) -> Result<()> {
+    if let Some(command) = args.command {
+        match command {
+            Action::Completions { shell, dir, tools } => {
+                completions(shell, dir.as_deref(), &tools)?
+            }
+            Action::Pipe { pipeline } => run_pipeline(&pipeline)?,
+            Action::Man { dir, tools } => man(dir.as_deref(), &tools)?,
+        }
+    } else if args.list {
+        for (name, ..) in TOOLS {
+            println!("{name}");
+        }
+    } else if let Some(dir) = &args.install {
+        install(dir)?;
+    } else {
+        return Err(Error::usage(
+            "runix",
+            "missing tool name, see `runix --list`",
+        ));
+    }
+
+    Ok(())
+}
+
 fn main() {
-    println!("Hello, world!");
+    let args: Vec<OsString> = env::args_os().collect();
+
+    // Invoked through a symlink, e.g. `grep -i foo`
+    let argv0 = args.first().and_then(|a| Path::new(a).file_stem());
+    let (tool, args) = match argv0.and_then(find_tool) {
+        Some(tool) => (Some(tool), args),
+        // Invoked as `runix grep -i foo`
+        None => match args.get(1).and_then(|a| find_tool(a)) {
+            Some(tool) => (Some(tool), args[1..].to_vec()),
+            None => (None, args),
+        },
+    };
+
+    let result = match tool {
+        Some(tool) => tool(args),
+        None => Args::try_parse_from(args)
+            .map_err(|e| Error::args("runix", e))
+            .and_then(run),
+    };
+
+    if let Err(e) = result {
+        e.exit();
+    }
 }
@@ -0,0 +1,295 @@
+//! In-process pipelines, as in `runix pipe 'grep x | cut -f1 | uniq -c'`.
+//! Every stage runs on its own thread. Its standard input and output,
+//! as seen by `open_file("-")` and `Output`, are bounded in-memory
+//! channels to the neighbouring stages
+
+use std::{
+    cell::RefCell,
+    ffi::OsString,
+    io::{self, BufRead, BufReader, Read, Write},
+    mem, panic,
+    rc::Rc,
+    sync::mpsc::{sync_channel, Receiver, SyncSender},
+    thread,
+};
+
+use crate::Result;
+
+/// Entry point of a tool, called with its command line
+pub type Tool = fn(Vec<OsString>) -> Result<()>;
+
+/// Chunks, of up to the `Output` buffer size, in flight between two stages
+const PIPE_CAPACITY: usize = 16;
+
+thread_local! {
+    static STDIN: RefCell<Option<Rc<RefCell<PipeReader>>>> = const { RefCell::new(None) };
+    static STDOUT: RefCell<Option<Rc<RefCell<PipeWriter>>>> = const { RefCell::new(None) };
+}
+
+struct PipeReader {
+    chunks: Receiver<Vec<u8>>,
+    chunk: Vec<u8>,
+    pos: usize,
+}
+
+impl Read for PipeReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.chunk.len() {
+            match self.chunks.recv() {
+                Ok(chunk) => {
+                    self.chunk = chunk;
+                    self.pos = 0;
+                }
+                // The previous stage is done
+                Err(_) => return Ok(0),
+            }
+        }
+
+        let n = buf.len().min(self.chunk.len() - self.pos);
+        buf[..n].copy_from_slice(&self.chunk[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+struct PipeWriter(SyncSender<Vec<u8>>);
+
+impl Write for PipeWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        // The next stage is done and dropped its end
+        self.0
+            .send(buf.to_vec())
+            .map_err(|_| io::Error::from(io::ErrorKind::BrokenPipe))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// A handle on the stage's end of a pipe. Opening "-" twice, as in
+/// `cat - -`, reads from the same pipe
+struct Shared<T>(Rc<RefCell<T>>);
+
+impl<T: Read> Read for Shared<T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.borrow_mut().read(buf)
+    }
+}
+
+impl<T: Write> Write for Shared<T> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.borrow_mut().flush()
+    }
+}
+
+/// Standard input of the current pipeline stage, if any
+pub(crate) fn stdin() -> Option<Box<dyn BufRead>> {
+    STDIN.with(|stdin| {
+        let pipe = stdin.borrow().clone()?;
+        Some(Box::new(BufReader::new(Shared(pipe))) as Box<dyn BufRead>)
+    })
+}
+
+/// Standard output of the current pipeline stage, if any
+pub(crate) fn stdout() -> Option<Box<dyn Write>> {
+    STDOUT.with(|stdout| {
+        let pipe = stdout.borrow().clone()?;
+        Some(Box::new(Shared(pipe)) as Box<dyn Write>)
+    })
+}
+
+/// Whether the current thread runs a pipeline stage, whose standard
+/// output is not the one of the process
+pub(crate) fn is_stage() -> bool {
+    STDOUT.with(|stdout| stdout.borrow().is_some())
+}
+
+/// Marks a stage that stopped because the next one closed its input
+struct BrokenPipe;
+
+/// Ends the current stage quietly, as SIGPIPE ends a process in a shell
+/// pipeline. Outside of a pipeline the process exits with status 0
+pub(crate) fn exit_stage() -> ! {
+    if is_stage() {
+        // resume_unwind skips the panic hook, so nothing is printed
+        panic::resume_unwind(Box::new(BrokenPipe));
+    }
+
+    std::process::exit(0)
+}
+
+fn redirect(stdin: Option<PipeReader>, stdout: Option<PipeWriter>) {
+    STDIN.with(|s| *s.borrow_mut() = stdin.map(|r| Rc::new(RefCell::new(r))));
+    STDOUT.with(|s| *s.borrow_mut() = stdout.map(|w| Rc::new(RefCell::new(w))));
+}
+
+/// Runs `stages` concurrently, every stage reading the output of the
+/// previous one. Errors of the other stages are reported on stderr, the
+/// result is the one of the last stage, as in a shell without pipefail
+pub fn run(stages: Vec<(Tool, Vec<OsString>)>) -> Result<()> {
+    let last = stages.len().saturating_sub(1);
+    let mut input = None;
+    let mut handles = Vec::new();
+    for (i, (tool, args)) in stages.into_iter().enumerate() {
+        let (output, next) = if i < last {
+            let (tx, rx) = sync_channel(PIPE_CAPACITY);
+            let reader = PipeReader {
+                chunks: rx,
+                chunk: Vec::new(),
+                pos: 0,
+            };
+            (Some(PipeWriter(tx)), Some(reader))
+        } else {
+            (None, None)
+        };
+
+        let stdin = mem::replace(&mut input, next);
+        handles.push(thread::spawn(move || {
+            redirect(stdin, output);
+            let result = tool(args);
+            // Close both ends now, so the neighbours see EOF or EPIPE
+            redirect(None, None);
+            result
+        }));
+    }
+
+    let mut status = Ok(());
+    for (i, handle) in handles.into_iter().enumerate() {
+        let result = handle.join().unwrap_or_else(|payload| {
+            if !payload.is::<BrokenPipe>() {
+                panic::resume_unwind(payload);
+            }
+            Ok(())
+        });
+
+        match result {
+            Err(e) if i < last => e.report(),
+            result => status = result,
+        }
+    }
+
+    status
+}
+
+/// Splits a pipeline into the words of every stage. Words are separated
+/// by blanks and quoted like in sh: '...' is literal, "..." honours \" and
+/// \\, and a backslash quotes the next character
+pub fn parse(line: &str) -> std::result::Result<Vec<Vec<String>>, String> {
+    let mut stages = Vec::new();
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '|' => {
+                words.extend(word.take());
+                if words.is_empty() {
+                    return Err("empty command in pipeline".to_string());
+                }
+                stages.push(mem::take(&mut words));
+            }
+            c if c.is_whitespace() => words.extend(word.take()),
+            '\'' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => word.push(c),
+                        None => return Err("unterminated quote in pipeline".to_string()),
+                    }
+                }
+            }
+            '"' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\')) => word.push(c),
+                            Some(c) => {
+                                word.push('\\');
+                                word.push(c);
+                            }
+                            None => return Err("unterminated quote in pipeline".to_string()),
+                        },
+                        Some(c) => word.push(c),
+                        None => return Err("unterminated quote in pipeline".to_string()),
+                    }
+                }
+            }
+            '\\' => word
+                .get_or_insert_with(String::new)
+                .push(chars.next().unwrap_or('\\')),
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+
+    words.extend(word);
+    if words.is_empty() {
+        return Err("empty command in pipeline".to_string());
+    }
+    stages.push(words);
+
+    Ok(stages)
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+    use crate::{Error, Output};
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            parse("grep -i error log.txt | cut -f1|uniq -c").unwrap(),
+            vec![
+                vec!["grep", "-i", "error", "log.txt"],
+                vec!["cut", "-f1"],
+                vec!["uniq", "-c"],
+            ]
+        );
+        assert_eq!(
+            parse(r#"grep 'a | b' | cut -d" " -f2 | grep "\"x\\" a\ b ''"#).unwrap(),
+            vec![
+                vec!["grep", "a | b"],
+                vec!["cut", "-d ", "-f2"],
+                vec!["grep", "\"x\\", "a b", ""],
+            ]
+        );
+
+        assert_eq!(parse("cat |").unwrap_err(), "empty command in pipeline");
+        assert_eq!(parse(" | wc").unwrap_err(), "empty command in pipeline");
+        assert_eq!(parse("").unwrap_err(), "empty command in pipeline");
+        assert_eq!(
+            parse("grep 'x").unwrap_err(),
+            "unterminated quote in pipeline"
+        );
+    }
+
+    #[test]
+    fn test_run() {
+        let stages: Vec<(Tool, Vec<OsString>)> = vec![
+            (
+                |_| {
+                    let mut out = Output::new();
+                    out.write_all(b"one\ntwo\n")
+                        .and_then(|_| out.flush())
+                        .map_err(|e| Error::io("test", "standard output", e))
+                },
+                vec![],
+            ),
+            (|_| Err(Error::Status(3)), vec![]),
+        ];
+        assert_eq!(run(stages).unwrap_err().exit_code(), 3);
+    }
+}
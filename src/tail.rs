@@ -0,0 +1,373 @@
+use std::{
+    collections::VecDeque,
+    ffi::OsString,
+    fmt::Display,
+    io::{self, BufRead, Read, Write},
+    str::FromStr,
+};
+
+use crate::{open_input, Error, Input, LineReader, Output, Result, Terminator};
+use clap::Parser;
+use memchr::memchr_iter;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TakeSize {
+    PlusZero,
+    Num(i64),
+}
+
+impl FromStr for TakeSize {
+    type Err = std::num::ParseIntError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        if s == "+0" {
+            return Ok(Self::PlusZero);
+        }
+
+        let mut n: i64 = s.parse()?;
+        if !s.starts_with('+') && n > 0 {
+            n = -n;
+        }
+        Ok(Self::Num(n))
+    }
+}
+
+impl Display for TakeSize {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::PlusZero => write!(f, "+0"),
+            Self::Num(n) => write!(f, "{n}"),
+        }
+    }
+}
+
+#[derive(Parser, Debug)]
+#[command(
+    name = "tail",
+    author = "protium",
+    version = "0.1.0",
+    about = "display the last part of a file"
+)]
+pub struct Args {
+    #[arg(
+        name = "files",
+        value_name = "FILES",
+        default_value = "-",
+        help = "Input file(s)"
+    )]
+    pub files: Vec<String>,
+
+    #[arg(
+        name = "lines",
+        short = 'n',
+        long = "lines",
+        help = "Number of lines",
+        default_value_t = TakeSize::Num(10),
+        conflicts_with = "bytes"
+    )]
+    pub lines: TakeSize,
+
+    #[arg(name = "bytes", short = 'c', long = "bytes", help = "Number of bytes")]
+    pub bytes: Option<TakeSize>,
+
+    #[arg(
+        name = "quiet",
+        short = 'q',
+        long = "quiet",
+        help = "never print headers giving file names",
+        default_value_t = false
+    )]
+    pub quiet: bool,
+
+    #[command(flatten)]
+    pub terminator: Terminator,
+}
+
+/// Writes the last (or, counting from `+N`, all but the first) lines or
+/// bytes of an input. The input is read once, keeping only the last lines
+/// or bytes, so that it may be a pipe
+#[derive(Clone, Debug)]
+pub struct Tail {
+    count: TakeSize,
+    bytes: bool,
+    terminator: u8,
+}
+
+impl Tail {
+    pub fn lines(count: TakeSize) -> Self {
+        Self {
+            count,
+            bytes: false,
+            terminator: b'\n',
+        }
+    }
+
+    pub fn bytes(count: TakeSize) -> Self {
+        Self {
+            count,
+            bytes: true,
+            terminator: b'\n',
+        }
+    }
+
+    /// Byte that ends every line, '\n' by default
+    pub fn terminator(mut self, byte: u8) -> Self {
+        self.terminator = byte;
+        self
+    }
+
+    pub fn write<R: BufRead, W: Write>(&self, mut input: R, mut out: W) -> io::Result<()> {
+        match (&self.count, self.bytes) {
+            (TakeSize::PlusZero, _) => io::copy(&mut input, &mut out).map(|_| ()),
+            (TakeSize::Num(0), _) => Ok(()),
+            (&TakeSize::Num(n), true) if n > 0 => {
+                io::copy(&mut input.by_ref().take(n as u64 - 1), &mut io::sink())?;
+                io::copy(&mut input, &mut out).map(|_| ())
+            }
+            (&TakeSize::Num(n), false) if n > 0 => {
+                print_lines_from(input, &mut out, n, self.terminator)
+            }
+            (&TakeSize::Num(n), true) => print_last_bytes(input, &mut out, n.unsigned_abs()),
+            (&TakeSize::Num(n), false) => {
+                print_last_lines(input, &mut out, n.unsigned_abs(), self.terminator)
+            }
+        }
+    }
+
+    /// Like `write`, over input that is already in memory. The start
+    /// offset is found without copying any line
+    pub fn write_in<W: Write>(&self, content: &[u8], mut out: W) -> io::Result<()> {
+        let total_bytes = content.len() as i64;
+        let start = if self.bytes {
+            get_start_index(&self.count, total_bytes)
+        } else {
+            let unterminated = !content.is_empty() && !content.ends_with(&[self.terminator]);
+            let total_lines = memchr_iter(self.terminator, content).count() + unterminated as usize;
+            get_start_index(&self.count, total_lines as i64).map(|start| match start {
+                0 => 0,
+                n => memchr_iter(self.terminator, content)
+                    .nth(n as usize - 1)
+                    .map_or(total_bytes, |i| i as i64 + 1),
+            })
+        };
+
+        match start {
+            Some(start) => out.write_all(&content[start as usize..]),
+            None => Ok(()),
+        }
+    }
+}
+
+pub fn run(args: Args) -> Result<()> {
+    let engine = match &args.bytes {
+        Some(count) => Tail::bytes(count.clone()),
+        None => Tail::lines(args.lines.clone()),
+    }
+    .terminator(args.terminator.byte());
+
+    let print_header = args.files.len() > 1 && !args.quiet;
+    let mut out = Output::new();
+    let mut failed = false;
+    for (i, f) in args.files.iter().enumerate() {
+        if let Err(e) = tail(f, i > 0, print_header, &engine, &mut out) {
+            eprintln!("{e}");
+            failed = true;
+        }
+    }
+
+    out.flush()
+        .map_err(|e| Error::io("tail", "standard output", e))?;
+
+    if failed {
+        return Err(Error::Status(1));
+    }
+
+    Ok(())
+}
+
+fn tail(
+    filename: &str,
+    separate: bool,
+    print_header: bool,
+    tail: &Tail,
+    out: &mut Output,
+) -> Result<()> {
+    let io_err = |e| Error::io("tail", filename, e);
+    let out_err = |e| Error::io("tail", "standard output", e);
+    let input = open_input(filename, false).map_err(io_err)?;
+    if separate {
+        writeln!(out).map_err(out_err)?;
+    }
+
+    if print_header {
+        writeln!(
+            out,
+            "==> {} <==",
+            if filename == "-" {
+                "standard input"
+            } else {
+                filename
+            }
+        )
+        .map_err(out_err)?;
+    }
+
+    match input {
+        Input::Mapped(map) => tail.write_in(&map, out),
+        Input::Stream(content) => tail.write(content, out),
+    }
+    .map_err(io_err)
+}
+
+fn get_start_index(take_size: &TakeSize, total: i64) -> Option<i64> {
+    match take_size {
+        TakeSize::PlusZero => {
+            if total > 0 {
+                Some(0)
+            } else {
+                None
+            }
+        }
+
+        TakeSize::Num(n) => {
+            let n = *n;
+            if n == 0 || total == 0 || n > total {
+                return None;
+            }
+
+            let start = if n < 0 { total + n } else { n - 1 };
+            Some(if start < 0 { 0 } else { start })
+        }
+    }
+}
+
+/// Writes the lines from the `start`th one, counted from 1
+fn print_lines_from(
+    content: impl BufRead,
+    out: &mut impl Write,
+    start: i64,
+    delim: u8,
+) -> io::Result<()> {
+    let mut content = LineReader::with_delimiter(content, delim);
+    let mut number = 1;
+    while let Some(line) = content.next_line()? {
+        if number >= start {
+            out.write_all(line)?;
+        }
+        number += 1;
+    }
+
+    Ok(())
+}
+
+/// Writes the last `count` lines, kept in a ring of buffers that are
+/// reused as lines fall out of it
+fn print_last_lines(
+    content: impl BufRead,
+    out: &mut impl Write,
+    count: u64,
+    delim: u8,
+) -> io::Result<()> {
+    let mut content = LineReader::with_delimiter(content, delim);
+    let mut last: VecDeque<Vec<u8>> = VecDeque::new();
+    while let Some(line) = content.next_line()? {
+        let mut buf = match last.len() as u64 == count {
+            true => last.pop_front().unwrap_or_default(),
+            false => Vec::new(),
+        };
+        buf.clear();
+        buf.extend_from_slice(line);
+        last.push_back(buf);
+    }
+
+    last.iter().try_for_each(|line| out.write_all(line))
+}
+
+/// Writes the last `count` bytes
+fn print_last_bytes(mut content: impl BufRead, out: &mut impl Write, count: u64) -> io::Result<()> {
+    let mut last = VecDeque::new();
+    loop {
+        let buf = content.fill_buf()?;
+        if buf.is_empty() {
+            break;
+        }
+
+        let keep = buf.len().min(count as usize);
+        let over = (last.len() + keep).saturating_sub(count as usize);
+        last.drain(..over);
+        last.extend(&buf[buf.len() - keep..]);
+        let n = buf.len();
+        content.consume(n);
+    }
+
+    let (front, back) = last.as_slices();
+    out.write_all(front)?;
+    out.write_all(back)
+}
+
+pub fn get_args<I, T>(args: I) -> Result<Args>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    Args::try_parse_from(args).map_err(|e| Error::args("tail", e))
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    #[test]
+    fn test_get_start_index() {
+        assert_eq!(get_start_index(&TakeSize::PlusZero, 0), None);
+        assert_eq!(get_start_index(&TakeSize::PlusZero, 10), Some(0));
+        assert_eq!(get_start_index(&TakeSize::Num(0), 0), None);
+        assert_eq!(get_start_index(&TakeSize::Num(-10), 100), Some(90));
+        assert_eq!(get_start_index(&TakeSize::Num(10), 100), Some(9));
+    }
+
+    #[test]
+    fn test_tail() {
+        let input = || io::Cursor::new(b"one\ntwo\nthree\n".to_vec());
+        let mut out = Vec::new();
+        Tail::lines(TakeSize::Num(-2))
+            .write(input(), &mut out)
+            .unwrap();
+        assert_eq!(out, b"two\nthree\n");
+
+        let mut out = Vec::new();
+        Tail::bytes(TakeSize::Num(3))
+            .write(input(), &mut out)
+            .unwrap();
+        assert_eq!(out, b"e\ntwo\nthree\n");
+
+        for (count, expected) in [
+            (TakeSize::Num(-2), &b"two\nthree"[..]),
+            (TakeSize::Num(2), b"two\nthree"),
+            (TakeSize::Num(-5), b"one\ntwo\nthree"),
+            (TakeSize::PlusZero, b"one\ntwo\nthree"),
+            (TakeSize::Num(0), b""),
+        ] {
+            let mut out = Vec::new();
+            Tail::lines(count)
+                .write_in(b"one\ntwo\nthree", &mut out)
+                .unwrap();
+            assert_eq!(out, expected);
+        }
+
+        // Read once through a buffer, as from a pipe, and from memory
+        let input = b"one\ntwo\nthree";
+        for count in [-20, -5, -2, 0, 1, 2, 5, 20] {
+            for tail in [
+                Tail::lines(TakeSize::Num(count)),
+                Tail::bytes(TakeSize::Num(count)),
+            ] {
+                let (mut streamed, mut mapped) = (Vec::new(), Vec::new());
+                tail.write(io::BufReader::with_capacity(3, &input[..]), &mut streamed)
+                    .unwrap();
+                tail.write_in(input, &mut mapped).unwrap();
+                assert_eq!(streamed, mapped, "{tail:?}");
+            }
+        }
+    }
+}
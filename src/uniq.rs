@@ -0,0 +1,126 @@
+use std::{
+    ffi::OsString,
+    fs::File,
+    io::{self, BufWriter, Write},
+};
+
+use crate::{chomp, open_file, Decompress, Error, LineReader, Output, Result, Terminator};
+use clap::Parser;
+
+#[derive(Parser, Debug)]
+#[clap(
+    name = "uniq",
+    author = "protium",
+    version = "0.1.0",
+    about = "Filter adjacent matching lines from INPUT (or standard input), writing to OUTPUT (or standard output)."
+)]
+pub struct Args {
+    #[arg(name = "input", default_value = "-", help = "Input file")]
+    pub input: String,
+
+    #[arg(name = "output", help = "Output file")]
+    pub output: Option<String>,
+
+    #[arg(
+        name = "count",
+        short,
+        long,
+        help = "Prefix lines by number of occurrences"
+    )]
+    pub count: bool,
+
+    #[arg(
+        name = "repeated",
+        short = 'd',
+        long,
+        help = "Only print duplicated lines"
+    )]
+    pub repeated: bool,
+
+    #[arg(name = "unique", short, long, help = "Only print unique lines")]
+    pub unique: bool,
+
+    #[command(flatten)]
+    pub decompress: Decompress,
+
+    #[command(flatten)]
+    pub terminator: Terminator,
+}
+
+fn print_count(out: &mut Box<dyn Write>, count: usize, line: &[u8]) -> io::Result<()> {
+    if count > 0 {
+        write!(out, "{count:7} ")?;
+        out.write_all(line)?;
+    }
+
+    Ok(())
+}
+
+fn print_repeated(out: &mut Box<dyn Write>, count: usize, line: &[u8]) -> io::Result<()> {
+    if count > 1 {
+        out.write_all(line)?;
+    }
+    Ok(())
+}
+
+fn print_unique(out: &mut Box<dyn Write>, count: usize, line: &[u8]) -> io::Result<()> {
+    if count == 1 {
+        out.write_all(line)?;
+    }
+    Ok(())
+}
+
+fn print_default(out: &mut Box<dyn Write>, count: usize, line: &[u8]) -> io::Result<()> {
+    if count > 0 {
+        out.write_all(line)?;
+    }
+
+    Ok(())
+}
+
+pub fn run(args: Args) -> Result<()> {
+    let in_err = |e| Error::io("uniq", &args.input, e);
+    let out_path = args.output.as_deref().unwrap_or("standard output");
+    let out_err = |e| Error::io("uniq", out_path, e);
+    let delim = args.terminator.byte();
+    let mut file = LineReader::with_delimiter(
+        open_file(&args.input, args.decompress.enabled()).map_err(in_err)?,
+        delim,
+    );
+    let mut count: usize = 0;
+    let mut previous = Vec::new();
+    let mut out: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(BufWriter::new(File::create(path).map_err(out_err)?)),
+        None => Box::new(Output::new()),
+    };
+
+    let print = match (args.count, args.repeated, args.unique) {
+        (true, _, _) => print_count,
+        (_, true, _) => print_repeated,
+        (_, _, true) => print_unique,
+        _ => print_default,
+    };
+
+    while let Some(line) = file.next_line().map_err(in_err)? {
+        if chomp(line, delim) != chomp(&previous, delim) {
+            print(&mut out, count, &previous).map_err(out_err)?;
+            previous.clear();
+            previous.extend_from_slice(line);
+            count = 0;
+        }
+
+        count += 1;
+    }
+
+    print(&mut out, count, &previous).map_err(out_err)?;
+    out.flush().map_err(out_err)?;
+    Ok(())
+}
+
+pub fn get_args<I, T>(args: I) -> Result<Args>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    Args::try_parse_from(args).map_err(|e| Error::args("uniq", e))
+}
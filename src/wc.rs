@@ -0,0 +1,224 @@
+use std::{
+    ffi::OsString,
+    io::{self, BufRead, Write},
+    ops::AddAssign,
+};
+
+use crate::{open_input, Decompress, Error, Input, LineReader, Output, Result};
+use clap::Parser;
+use memchr::memchr_iter;
+
+#[derive(Parser, Debug)]
+#[clap(
+    name = "wc",
+    author = "protium",
+    version = "0.1.0",
+    about = "Counts lines, words and bytes"
+)]
+pub struct Args {
+    #[arg(name = "files", default_value = "-", help = "Input file(s)")]
+    pub files: Vec<String>,
+
+    #[arg(
+        name = "count",
+        short,
+        long,
+        help = "The number of bytes in each input",
+        conflicts_with = "chars"
+    )]
+    pub bytes: bool,
+
+    #[arg(
+        name = "chars",
+        short = 'm',
+        long = "chars",
+        help = "The number of chars in each input"
+    )]
+    pub chars: bool,
+
+    #[arg(name = "lines", short, long, help = "The number of line in each input")]
+    pub lines: bool,
+
+    #[arg(
+        name = "words",
+        short,
+        long,
+        help = "The number of words in each input"
+    )]
+    pub words: bool,
+
+    #[command(flatten)]
+    pub decompress: Decompress,
+}
+
+/// Counts of a single input, as reported by wc
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FileInfo {
+    pub bytes: usize,
+    pub chars: usize,
+    pub lines: usize,
+    pub words: usize,
+}
+
+impl AddAssign for FileInfo {
+    fn add_assign(&mut self, other: Self) {
+        self.bytes += other.bytes;
+        self.chars += other.chars;
+        self.lines += other.lines;
+        self.words += other.words;
+    }
+}
+
+/// Counts the newlines, words, characters and bytes of `file`
+pub fn count(file: impl BufRead) -> io::Result<FileInfo> {
+    let mut ret = FileInfo::default();
+
+    // Words never span a newline, so lines can be counted one by one
+    let mut file = LineReader::new(file);
+    while let Some(line) = file.next_line()? {
+        ret += count_bytes(line);
+    }
+
+    Ok(ret)
+}
+
+/// Counts the newlines, words, characters and bytes of `content` in one
+/// pass, e.g. over a mapped file
+pub fn count_bytes(content: &[u8]) -> FileInfo {
+    let mut ret = FileInfo {
+        bytes: content.len(),
+        lines: memchr_iter(b'\n', content).count(),
+        ..Default::default()
+    };
+
+    // Invalid UTF-8 sequences are not characters but still part of a word
+    let mut in_word = false;
+    for chunk in content.utf8_chunks() {
+        for c in chunk.valid().chars() {
+            ret.chars += 1;
+            if c.is_whitespace() {
+                in_word = false;
+            } else if !in_word {
+                in_word = true;
+                ret.words += 1;
+            }
+        }
+
+        if !chunk.invalid().is_empty() && !in_word {
+            in_word = true;
+            ret.words += 1;
+        }
+    }
+
+    ret
+}
+
+fn format_info(flag: bool, value: usize) -> String {
+    if flag {
+        return format!("{:>5}", value).to_string();
+    }
+
+    String::new()
+}
+
+fn format_line(args: &Args, info: &FileInfo) -> String {
+    let mut out = String::new();
+    out.push_str(&format_info(args.lines, info.lines));
+    out.push_str(&format_info(args.words, info.words));
+    out.push_str(&format_info(args.bytes, info.bytes));
+    out.push_str(&format_info(args.chars, info.chars));
+    out
+}
+
+pub fn run(args: Args) -> Result<()> {
+    let mut total = FileInfo::default();
+    let mut stdout = Output::new();
+    let out_err = |e| Error::io("wc", "standard output", e);
+    let mut failed = false;
+    for f in &args.files {
+        let info = open_input(f, args.decompress.enabled()).and_then(|input| match input {
+            Input::Mapped(map) => Ok(count_bytes(&map)),
+            Input::Stream(content) => count(content),
+        });
+
+        match info {
+            Err(e) => {
+                eprintln!("{}", Error::io("wc", f, e));
+                failed = true;
+            }
+            Ok(info) => {
+                writeln!(
+                    stdout,
+                    "{} {}",
+                    format_line(&args, &info),
+                    if f == "-" { "".to_string() } else { f.clone() }
+                )
+                .map_err(out_err)?;
+
+                total += info;
+            }
+        }
+    }
+
+    if args.files.len() > 1 {
+        writeln!(stdout, "{} total", format_line(&args, &total)).map_err(out_err)?;
+    }
+
+    stdout.flush().map_err(out_err)?;
+
+    if failed {
+        return Err(Error::Status(1));
+    }
+
+    Ok(())
+}
+
+pub fn get_args<I, T>(args: I) -> Result<Args>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    let mut ret = Args::try_parse_from(args).map_err(|e| Error::args("wc", e))?;
+    if [ret.lines, ret.chars, ret.bytes, ret.words]
+        .iter()
+        .all(|v| !v)
+    {
+        ret.bytes = true;
+        ret.lines = true;
+        ret.words = true;
+    }
+    Ok(ret)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{count, count_bytes, FileInfo};
+    use std::io::Cursor;
+
+    #[test]
+    fn test_count() {
+        let text = "Join the dark side 🌕.\r\n";
+        let info = count(Cursor::new(text));
+        assert!(info.is_ok());
+
+        let expected = FileInfo {
+            lines: 1,
+            words: 5,
+            chars: 23,
+            bytes: 26,
+        };
+
+        assert_eq!(info.unwrap(), expected);
+
+        let info = count(Cursor::new(b"caf\xe9 au\xff lait\n\xfe"));
+        let expected = FileInfo {
+            lines: 1,
+            words: 4,
+            chars: 12,
+            bytes: 15,
+        };
+
+        assert_eq!(info.unwrap(), expected);
+        assert_eq!(count_bytes(b"caf\xe9 au\xff lait\n\xfe"), expected);
+    }
+}
@@ -22,8 +22,8 @@ fn cat_skips_missing_file() -> TestResult {
     let mut cmd = Command::cargo_bin("cat")?;
     cmd.args(["missing.txt"])
         .assert()
-        .success()
-        .stderr(predicate::str::contains("No such file"));
+        .failure()
+        .stderr(predicate::str::contains("cat: missing.txt: No such file"));
 
     Ok(())
 }
@@ -63,6 +63,21 @@ fn cat_no_blanks() -> TestResult {
     Ok(())
 }
 
+#[test]
+fn cat_binary() -> TestResult {
+    let mut cmd = Command::cargo_bin("cat")?;
+    let input: &[u8] = b"caf\xe9\n\x00\xff\xfe\n";
+    cmd.args(["-n"])
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout(predicate::eq(
+            b"     1\tcaf\xe9\n     2\t\x00\xff\xfe\n" as &[u8],
+        ));
+
+    Ok(())
+}
+
 #[test]
 fn cat_stdin() -> TestResult {
     let mut cmd = Command::cargo_bin("cat")?;
@@ -70,7 +85,66 @@ fn cat_stdin() -> TestResult {
         .write_stdin("test")
         .assert()
         .success()
-        .stdout("test\n");
+        .stdout("test");
+
+    Ok(())
+}
+
+#[test]
+fn cat_decompresses() -> TestResult {
+    let expected = fs::read_to_string("tests/inputs/compressed.txt")?;
+    for ext in ["gz", "xz", "zst"] {
+        let mut cmd = Command::cargo_bin("cat")?;
+        cmd.args([format!("tests/inputs/compressed.txt.{ext}")])
+            .assert()
+            .success()
+            .stdout(expected.clone());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn cat_no_decompress() -> TestResult {
+    let mut cmd = Command::cargo_bin("cat")?;
+    let expected = fs::read("tests/inputs/compressed.txt.gz")?;
+    cmd.args(["--no-decompress", "tests/inputs/compressed.txt.gz"])
+        .assert()
+        .success()
+        .stdout(expected);
+
+    Ok(())
+}
+
+#[test]
+fn cat_broken_pipe() -> TestResult {
+    use std::io::{Read, Write};
+    use std::process::Stdio;
+
+    let mut child = std::process::Command::new(assert_cmd::cargo::cargo_bin("cat"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    let mut stdin = child.stdin.take().unwrap();
+    let writer = std::thread::spawn(move || {
+        let line = "a line that is repeated until the reader goes away\n".repeat(1024);
+        for _ in 0..1024 {
+            if stdin.write_all(line.as_bytes()).is_err() {
+                break;
+            }
+        }
+    });
+
+    // Read a little and close the pipe, like `cat big.log | head` does
+    let mut buf = [0; 16];
+    child.stdout.take().unwrap().read_exact(&mut buf)?;
+
+    let output = child.wait_with_output()?;
+    writer.join().unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stderr)?, "");
 
     Ok(())
 }
@@ -69,3 +69,15 @@ fn comm_case_insensitive() -> TestResult {
 
     Ok(())
 }
+
+#[test]
+fn comm_zero_terminated() -> TestResult {
+    let mut cmd = Command::cargo_bin("comm")?;
+    cmd.args(["-z", "-", "tests/inputs/comm_zero_terminated.txt"])
+        .write_stdin("a\0b\nx\0d\0")
+        .assert()
+        .success()
+        .stdout("a\0\t\tb\nx\0\tc\0d\0");
+
+    Ok(())
+}
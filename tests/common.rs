@@ -1 +1,57 @@
+// Not every test crate uses every helper
+#![allow(dead_code)]
+
+use rand::{distributions::Alphanumeric, Rng};
+use std::{
+    env,
+    ffi::OsStr,
+    fs, io,
+    ops::Deref,
+    path::{Path, PathBuf},
+};
+
 pub type TestResult = Result<(), Box<dyn std::error::Error>>;
+
+/// A fresh directory under the system temporary directory, removed with
+/// everything in it when dropped, even after a failed assertion
+pub struct TempDir(PathBuf);
+
+impl Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for TempDir {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<OsStr> for TempDir {
+    fn as_ref(&self) -> &OsStr {
+        self.0.as_os_str()
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        // A test may have removed it already
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Creates a `TempDir` named `prefix` and a random suffix
+pub fn temp_dir(prefix: &str) -> io::Result<TempDir> {
+    let suffix: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(10)
+        .map(char::from)
+        .collect();
+    let dir = env::temp_dir().join(format!("{prefix}-{suffix}"));
+    fs::create_dir(&dir)?;
+
+    Ok(TempDir(dir))
+}
@@ -11,7 +11,7 @@ fn cut_skips_missing_file() -> TestResult {
     let mut cmd = Command::cargo_bin("cut")?;
     cmd.args(["-b", "1", "missing.txt"])
         .assert()
-        .success()
+        .failure()
         .stderr(predicate::str::contains("No such file"));
 
     Ok(())
@@ -20,7 +20,7 @@ fn cut_skips_missing_file() -> TestResult {
 #[test]
 fn cut_bytes() -> TestResult {
     let mut cmd = Command::cargo_bin("cut")?;
-    let expected = fs::read_to_string("tests/inputs/cut_bytes_expected.txt")?;
+    let expected = fs::read("tests/inputs/cut_bytes_expected.txt")?;
     cmd.args(["-b", "17-28,29-31", "tests/inputs/cut_bytes.txt"])
         .assert()
         .success()
@@ -76,3 +76,15 @@ fn cut_fields_tabs() -> TestResult {
 
     Ok(())
 }
+
+#[test]
+fn cut_zero_terminated() -> TestResult {
+    let mut cmd = Command::cargo_bin("cut")?;
+    cmd.args(["-z", "-f", "1"])
+        .write_stdin("a\tb\nc\0d\te\0")
+        .assert()
+        .success()
+        .stdout("b\nc\0e\0");
+
+    Ok(())
+}
@@ -1,3 +1,7 @@
+// The expected outputs are built with concat!, one path per line, even
+// when there is a single path
+#![allow(clippy::useless_concat)]
+
 use assert_cmd::Command;
 use predicates::prelude::*;
 use std::fs;
@@ -11,7 +15,7 @@ fn find_skips_missing_paths() -> TestResult {
     let mut cmd = Command::cargo_bin("find")?;
     cmd.args(["missing", "--", "expr"])
         .assert()
-        .success()
+        .failure()
         .stderr(predicate::str::contains("No such file"));
 
     Ok(())
@@ -85,3 +89,316 @@ fn find_depth() -> TestResult {
 
     Ok(())
 }
+
+/// Lines of the output in sorted order, as the walk follows directory order
+fn sorted_lines(output: &[u8]) -> Vec<String> {
+    let mut lines: Vec<String> = String::from_utf8_lossy(output)
+        .lines()
+        .map(String::from)
+        .collect();
+    lines.sort();
+    lines
+}
+
+#[test]
+fn find_expression() -> TestResult {
+    let mut cmd = Command::cargo_bin("find")?;
+    let output = cmd
+        .args([
+            "tests/find",
+            "--",
+            "(",
+            "a",
+            "-o",
+            "b",
+            ")",
+            "!",
+            "-type",
+            "f",
+        ])
+        .assert()
+        .success();
+    assert_eq!(
+        sorted_lines(&output.get_output().stdout),
+        ["tests/find/a", "tests/find/b", "tests/find/b/a"]
+    );
+
+    let mut cmd = Command::cargo_bin("find")?;
+    let output = cmd
+        .args(["-d", "1", "tests/find", "--", "^b$", "-or", "-type", "f"])
+        .assert()
+        .success();
+    assert_eq!(
+        sorted_lines(&output.get_output().stdout),
+        ["tests/find/a.txt", "tests/find/b"]
+    );
+
+    let mut cmd = Command::cargo_bin("find")?;
+    cmd.args(["tests/find", "--", "(", "a", "-o"])
+        .assert()
+        .failure()
+        .stderr("find: invalid expression: expected an expression after '-o'\n");
+
+    Ok(())
+}
+
+/// A fresh directory of files with various sizes and permissions
+fn tree() -> Result<TempDir, Box<dyn std::error::Error>> {
+    let dir = temp_dir("find")?;
+    fs::create_dir_all(dir.join("empty"))?;
+    fs::create_dir_all(dir.join("full"))?;
+    fs::write(dir.join("full/small"), "small\n")?;
+    fs::write(dir.join("large"), vec![b'x'; 3000])?;
+    fs::write(dir.join("zero"), "")?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(dir.join("large"), fs::Permissions::from_mode(0o754))?;
+        fs::set_permissions(dir.join("zero"), fs::Permissions::from_mode(0o600))?;
+    }
+
+    Ok(dir)
+}
+
+#[test]
+fn find_metadata() -> TestResult {
+    let dir = tree()?;
+    let find = |expression: &[&str]| -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        let output = Command::cargo_bin("find")?
+            .arg(&dir)
+            .arg("--")
+            .args(expression)
+            .assert()
+            .success();
+        let prefix = format!("{}/", dir.display());
+        Ok(sorted_lines(&output.get_output().stdout)
+            .into_iter()
+            .filter_map(|line| line.strip_prefix(&prefix).map(String::from))
+            .collect())
+    };
+
+    assert_eq!(find(&["-empty"])?, ["empty", "zero"]);
+    assert_eq!(
+        find(&["-type", "f", "(", "-size", "+1", "-o", "-size", "6c", ")"])?,
+        ["full/small", "large"]
+    );
+    assert_eq!(find(&["-type", "f", "-size", "-1k"])?, ["zero"]);
+    assert_eq!(
+        find(&["-type", "f", "-mmin", "-60"])?,
+        ["full/small", "large", "zero"]
+    );
+    assert!(find(&["-mtime", "+0"])?.is_empty());
+    #[cfg(unix)]
+    {
+        assert_eq!(find(&["-perm", "754"])?, ["large"]);
+        assert_eq!(find(&["-type", "f", "-perm", "/g+x,o+x"])?, ["large"]);
+        assert_eq!(find(&["-type", "f", "!", "-perm", "-u+r,g+r"])?, ["zero"]);
+    }
+
+    Command::cargo_bin("find")?
+        .args(["tests/find", "--", "-newer", "missing"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("find: missing: No such file"));
+
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn find_actions() -> TestResult {
+    let dir = tree()?;
+    let root = dir.display().to_string();
+
+    let mut cmd = Command::cargo_bin("find")?;
+    cmd.arg(&dir)
+        .args(["--", "^small$", "-exec", "cat", "{}", ";", "-print0"])
+        .args(["-o", "^zero$", "-printf", r"%f %s %m %M\n"]);
+    let output = cmd.assert().success();
+    let mut stdout = String::from_utf8(output.get_output().stdout.clone())?;
+    let mut lines: Vec<&str> = stdout.split_inclusive(['\n', '\0']).collect();
+    lines.sort();
+    assert_eq!(
+        lines,
+        [
+            format!("{root}/full/small\0"),
+            "small\n".to_string(),
+            "zero 0 600 -rw-------\n".to_string(),
+        ]
+    );
+
+    // All the files in one command
+    let mut cmd = Command::cargo_bin("find")?;
+    let output = cmd
+        .arg(&dir)
+        .args(["--", "-type", "f", "-exec", "wc", "-c", "{}", "+"])
+        .assert()
+        .success();
+    stdout = String::from_utf8(output.get_output().stdout.clone())?;
+    assert!(stdout.contains("3006 total\n"), "{stdout}");
+
+    let mut cmd = Command::cargo_bin("find")?;
+    cmd.arg(&dir)
+        .args([
+            "--", "-type", "f", "-execdir", "test", "-f", "{}", ";", "-printf", r"%P\n",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("full/small\n"));
+
+    let mut cmd = Command::cargo_bin("find")?;
+    cmd.arg(&dir)
+        .args(["--", "^full$", "-delete"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot delete"));
+
+    // -prune could not keep anything from -delete
+    let mut cmd = Command::cargo_bin("find")?;
+    cmd.arg(&dir)
+        .args(["-path", "*/full", "-prune", "-o", "-delete"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("-prune does nothing under -depth"));
+    assert!(dir.join("full/small").exists());
+
+    let mut cmd = Command::cargo_bin("find")?;
+    cmd.arg(&dir)
+        .args(["--", "-delete"])
+        .assert()
+        .success()
+        .stdout("");
+    assert!(!dir.exists());
+
+    Ok(())
+}
+
+#[test]
+fn find_globs() -> TestResult {
+    let find = |args: &[&str]| -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        let output = Command::cargo_bin("find")?.args(args).assert().success();
+        Ok(sorted_lines(&output.get_output().stdout))
+    };
+
+    assert_eq!(
+        find(&["tests/find"])?,
+        [
+            "tests/find",
+            "tests/find/a",
+            "tests/find/a.txt",
+            "tests/find/a/a.txt",
+            "tests/find/b",
+            "tests/find/b/a",
+            "tests/find/b/a/a.txt",
+        ]
+    );
+    assert_eq!(
+        find(&["tests/find", "-name", "*.txt", "!", "-path", "*/b/*"])?,
+        ["tests/find/a.txt", "tests/find/a/a.txt"]
+    );
+    assert_eq!(
+        find(&["-t", "d", "tests/find", "-iname", "[AB]"])?,
+        ["tests/find/a", "tests/find/b", "tests/find/b/a"]
+    );
+    assert_eq!(
+        find(&["tests/find", "-iwholename", "*/B/*.TXT"])?,
+        ["tests/find/b/a/a.txt"]
+    );
+    assert_eq!(
+        find(&[
+            "tests/find",
+            "-regextype",
+            "egrep",
+            "-regex",
+            r"tests/find/(a|b)"
+        ])?,
+        ["tests/find/a", "tests/find/b"]
+    );
+    assert_eq!(
+        find(&["tests/find", "-iregex", ".*/A.TXT", "-type", "f", "^a"])?,
+        [
+            "tests/find/a.txt",
+            "tests/find/a/a.txt",
+            "tests/find/b/a/a.txt",
+        ]
+    );
+
+    Command::cargo_bin("find")?
+        .args(["tests/find", "-name", "[a"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("unclosed character class"));
+
+    Ok(())
+}
+
+#[test]
+fn find_traversal() -> TestResult {
+    let mut cmd = Command::cargo_bin("find")?;
+    let expected = concat!(
+        "tests/find/a\n",
+        "tests/find/a/a.txt\n",
+        "tests/find/a.txt\n",
+        "tests/find/b\n",
+        "tests/find/b/a\n",
+        "tests/find/b/a/a.txt\n",
+    );
+    cmd.args(["--sort", "tests/find", "-mindepth", "1"])
+        .assert()
+        .success()
+        .stdout(expected);
+
+    let mut cmd = Command::cargo_bin("find")?;
+    let expected = concat!("tests/find/a.txt\n", "tests/find/b/a/a.txt\n");
+    cmd.args(["-s", "tests/find", "-path", "tests/find/a", "-prune"])
+        .args(["-o", "-type", "f", "-print"])
+        .assert()
+        .success()
+        .stdout(expected);
+
+    let mut cmd = Command::cargo_bin("find")?;
+    let expected = concat!(
+        "tests/find/a/a.txt\n",
+        "tests/find/a\n",
+        "tests/find/a.txt\n"
+    );
+    cmd.args(["-s", "tests/find", "-xdev", "-maxdepth", "2", "-depth"])
+        .args(["-path", "*/b*", "-prune", "-o", "-mindepth", "1", "-print"])
+        .assert()
+        .success()
+        .stdout(expected);
+
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn find_follow_links() -> TestResult {
+    let dir = tree()?;
+    std::os::unix::fs::symlink("..", dir.join("full/up"))?;
+    std::os::unix::fs::symlink(&dir, dir.join("root"))?;
+
+    let mut cmd = Command::cargo_bin("find")?;
+    let output = cmd.arg("-s").arg(dir.join("root")).assert().success();
+    assert_eq!(sorted_lines(&output.get_output().stdout).len(), 1);
+
+    let mut cmd = Command::cargo_bin("find")?;
+    let output = cmd
+        .args(["-s", "-H"])
+        .arg(dir.join("root"))
+        .args(["-maxdepth", "1", "-type", "l"])
+        .assert()
+        .success();
+    assert_eq!(sorted_lines(&output.get_output().stdout).len(), 1);
+
+    let mut cmd = Command::cargo_bin("find")?;
+    cmd.args(["-s", "-L"])
+        .arg(&dir)
+        .args(["-name", "small"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("File system loop detected"));
+
+    Ok(())
+}
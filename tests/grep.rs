@@ -1,6 +1,10 @@
 use assert_cmd::Command;
 use predicates::prelude::*;
-use std::fs;
+use std::{
+    fs,
+    io::{BufRead, BufReader, Write},
+    process::Stdio,
+};
 
 mod common;
 
@@ -10,9 +14,88 @@ use common::*;
 fn grep_skips_missing_file() -> TestResult {
     let mut cmd = Command::cargo_bin("grep")?;
     cmd.args(["test", "missing.txt"])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("grep: missing.txt: No such file"));
+
+    Ok(())
+}
+
+#[test]
+fn grep_no_match() -> TestResult {
+    let mut cmd = Command::cargo_bin("grep")?;
+    cmd.args(["missing", "tests/inputs/grep/a.txt"])
+        .assert()
+        .code(1)
+        .stdout("");
+
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn grep_special_files() -> TestResult {
+    // Standard input is a pipe here, as <(cmd) would be
+    let mut cmd = Command::cargo_bin("grep")?;
+    cmd.args(["foo", "/dev/stdin"])
+        .write_stdin("foo\nbar\n")
         .assert()
         .success()
-        .stderr(predicate::str::contains("No such file"));
+        .stdout("foo\n");
+
+    let mut cmd = Command::cargo_bin("grep")?;
+    cmd.args(["-c", ".", "/dev/null"])
+        .assert()
+        .code(1)
+        .stdout("0\n");
+
+    // A FIFO is opened once: the writer is gone by the time it is read
+    let dir = temp_dir("grep")?;
+    let fifo = dir.join("fifo");
+    let made = std::process::Command::new("mkfifo").arg(&fifo).status()?;
+    assert!(made.success());
+    let writer = {
+        let fifo = fifo.clone();
+        std::thread::spawn(move || fs::write(fifo, "foo\nbar\n"))
+    };
+    let mut cmd = Command::cargo_bin("grep")?;
+    cmd.arg("foo").arg(&fifo).assert().success().stdout("foo\n");
+    writer.join().map_err(|_| "writer panicked")??;
+
+    Ok(())
+}
+
+#[test]
+fn grep_latin1() -> TestResult {
+    let mut cmd = Command::cargo_bin("grep")?;
+    let input: &[u8] = b"caf\xe9 ok\nna\xefve\n\xff error\n";
+    cmd.args(["ok|error"])
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout(predicate::eq(b"caf\xe9 ok\n\xff error\n" as &[u8]));
+
+    Ok(())
+}
+
+#[test]
+fn grep_compressed() -> TestResult {
+    let mut cmd = Command::cargo_bin("grep")?;
+    cmd.args(["second", "tests/inputs/compressed.txt.xz"])
+        .assert()
+        .success()
+        .stdout("second line\n");
+
+    Ok(())
+}
+
+#[test]
+fn grep_null_data() -> TestResult {
+    let mut cmd = Command::cargo_bin("grep")?;
+    cmd.args(["--null-data", "^t", "tests/inputs/zero_terminated.txt"])
+        .assert()
+        .success()
+        .stdout("two\nlines\0three\0");
 
     Ok(())
 }
@@ -64,3 +147,715 @@ fn grep_recursive() -> TestResult {
 
     Ok(())
 }
+
+#[test]
+fn grep_line_numbers_and_offsets() -> TestResult {
+    let mut cmd = Command::cargo_bin("grep")?;
+    cmd.args(["-n", "-b", "INFO", "tests/inputs/grep/b.txt"])
+        .assert()
+        .success()
+        .stdout("2:35:somefile.lua 12:23 [INFO] message\n3:69:somefile.lua 13:01 [INFO] message\n");
+
+    Ok(())
+}
+
+#[test]
+fn grep_filename_prefix() -> TestResult {
+    let mut cmd = Command::cargo_bin("grep")?;
+    cmd.args(["-H", "ERROR", "tests/inputs/grep/b.txt"])
+        .assert()
+        .success()
+        .stdout("tests/inputs/grep/b.txt:somefile.lua 16:22 [ERROR] message\n");
+
+    let mut cmd = Command::cargo_bin("grep")?;
+    cmd.args([
+        "-h",
+        "-c",
+        "something",
+        "tests/inputs/grep/a.txt",
+        "tests/inputs/grep/b.txt",
+    ])
+    .assert()
+    .success()
+    .stdout("1\n0\n");
+
+    Ok(())
+}
+
+#[test]
+fn grep_list_files() -> TestResult {
+    let files = ["tests/inputs/grep/a.txt", "tests/inputs/grep/b.txt"];
+    let mut cmd = Command::cargo_bin("grep")?;
+    cmd.arg("-l")
+        .arg("ERROR")
+        .args(files)
+        .assert()
+        .success()
+        .stdout("tests/inputs/grep/b.txt\n");
+
+    let mut cmd = Command::cargo_bin("grep")?;
+    cmd.arg("-L")
+        .arg("ERROR")
+        .args(files)
+        .assert()
+        .success()
+        .stdout("tests/inputs/grep/a.txt\n");
+
+    // The exit status tells whether any line was selected, listed or not
+    let mut cmd = Command::cargo_bin("grep")?;
+    cmd.arg("-L")
+        .arg("e")
+        .args(files)
+        .assert()
+        .success()
+        .stdout("");
+
+    let mut cmd = Command::cargo_bin("grep")?;
+    cmd.arg("-L")
+        .arg("missing")
+        .args(files)
+        .assert()
+        .code(1)
+        .stdout("tests/inputs/grep/a.txt\ntests/inputs/grep/b.txt\n");
+
+    Ok(())
+}
+
+#[test]
+fn grep_quiet() -> TestResult {
+    let mut cmd = Command::cargo_bin("grep")?;
+    cmd.args(["-q", "ERROR", "missing.txt", "tests/inputs/grep/b.txt"])
+        .assert()
+        .success()
+        .stdout("")
+        .stderr(predicate::str::contains("grep: missing.txt: No such file"));
+
+    let mut cmd = Command::cargo_bin("grep")?;
+    cmd.args([
+        "-q",
+        "-s",
+        "ERROR",
+        "missing.txt",
+        "tests/inputs/grep/a.txt",
+    ])
+    .assert()
+    .code(2)
+    .stdout("")
+    .stderr("");
+
+    Ok(())
+}
+
+#[test]
+fn grep_context() -> TestResult {
+    let mut cmd = Command::cargo_bin("grep")?;
+    let expected = fs::read_to_string("tests/inputs/grep_context_expected.txt")?;
+    cmd.args(["-n", "-C", "1", "the", "tests/inputs/grep/a.txt"])
+        .assert()
+        .success()
+        .stdout(expected);
+
+    let mut cmd = Command::cargo_bin("grep")?;
+    cmd.args([
+        "-A",
+        "1",
+        "-v",
+        "INFO",
+        "tests/inputs/grep/b.txt",
+        "tests/inputs/grep/b.txt",
+    ])
+    .assert()
+    .success()
+    .stdout(concat!(
+        "tests/inputs/grep/b.txt:somefile.lua 16:22 [ERROR] message\n",
+        "tests/inputs/grep/b.txt-somefile.lua 12:23 [INFO] message\n",
+        "--\n",
+        "tests/inputs/grep/b.txt:somefile.lua 16:22 [ERROR] message\n",
+        "tests/inputs/grep/b.txt-somefile.lua 12:23 [INFO] message\n",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn grep_max_count() -> TestResult {
+    let mut cmd = Command::cargo_bin("grep")?;
+    cmd.args(["-m", "2", "-n", "the", "tests/inputs/grep/a.txt"])
+        .assert()
+        .success()
+        .stdout("1:Overhead the albatross\n2:Hangs motionless upon the air\n");
+
+    let mut cmd = Command::cargo_bin("grep")?;
+    cmd.args(["-m1", "-A1", "a"])
+        .write_stdin("a\na\nb\n")
+        .assert()
+        .success()
+        .stdout("a\na\n");
+
+    let mut cmd = Command::cargo_bin("grep")?;
+    cmd.args(["-m", "0", "the", "tests/inputs/grep/a.txt"])
+        .assert()
+        .code(1)
+        .stdout("");
+
+    Ok(())
+}
+
+#[test]
+fn grep_line_buffered() -> TestResult {
+    // The first match must show up while stdin is still open
+    let mut child = std::process::Command::new(assert_cmd::cargo::cargo_bin("grep"))
+        .args(["--line-buffered", "ERROR"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()?;
+    let mut stdin = child.stdin.take().ok_or("no stdin")?;
+    let mut stdout = BufReader::new(child.stdout.take().ok_or("no stdout")?);
+
+    stdin.write_all(b"INFO started\nERROR failed\n")?;
+    let mut line = String::new();
+    stdout.read_line(&mut line)?;
+    assert_eq!(line, "ERROR failed\n");
+
+    drop(stdin);
+    assert!(child.wait()?.success());
+    Ok(())
+}
+
+#[test]
+fn grep_multiple_patterns() -> TestResult {
+    let mut cmd = Command::cargo_bin("grep")?;
+    cmd.args(["-e", "albatross", "-e", "^Comes", "tests/inputs/grep/a.txt"])
+        .assert()
+        .success()
+        .stdout("Overhead the albatross\nComes willowing across the sand\n");
+
+    Ok(())
+}
+
+#[test]
+fn grep_pattern_file() -> TestResult {
+    let files = ["tests/inputs/grep/a.txt", "tests/inputs/grep/b.txt"];
+    let mut cmd = Command::cargo_bin("grep")?;
+    cmd.args(["-F", "-f", "tests/inputs/grep_patterns.txt"])
+        .args(files)
+        .assert()
+        .success()
+        .stdout(concat!(
+            "tests/inputs/grep/a.txt:Overhead the albatross\n",
+            "tests/inputs/grep/b.txt:somefile.lua 16:22 [ERROR] message\n",
+        ));
+
+    // Without -F, [ERROR] is a character class
+    let mut cmd = Command::cargo_bin("grep")?;
+    cmd.args(["-c", "-f", "tests/inputs/grep_patterns.txt"])
+        .args(files)
+        .assert()
+        .success()
+        .stdout("tests/inputs/grep/a.txt:1\ntests/inputs/grep/b.txt:3\n");
+
+    Ok(())
+}
+
+#[test]
+fn grep_fixed_strings_ignore_case() -> TestResult {
+    let mut cmd = Command::cargo_bin("grep")?;
+    cmd.args(["-F", "-i", "-e", "[info]", "-e", "ÉCHO"])
+        .write_stdin("a [INFO] line\nan écho\n[inf]o\n")
+        .assert()
+        .success()
+        .stdout("a [INFO] line\nan écho\n");
+
+    Ok(())
+}
+
+#[test]
+fn grep_color() -> TestResult {
+    let mut cmd = Command::cargo_bin("grep")?;
+    cmd.args([
+        "--color=always",
+        "-H",
+        "-n",
+        "ERROR",
+        "tests/inputs/grep/b.txt",
+    ])
+    .env_remove("GREP_COLORS")
+    .assert()
+    .success()
+    .stdout(concat!(
+        "\x1b[35m\x1b[Ktests/inputs/grep/b.txt\x1b[m\x1b[K",
+        "\x1b[36m\x1b[K:\x1b[m\x1b[K",
+        "\x1b[32m\x1b[K1\x1b[m\x1b[K",
+        "\x1b[36m\x1b[K:\x1b[m\x1b[K",
+        "somefile.lua 16:22 [\x1b[01;31m\x1b[KERROR\x1b[m\x1b[K] message\n",
+    ));
+
+    // Selected lines do not match with -v, so nothing is highlighted
+    let mut cmd = Command::cargo_bin("grep")?;
+    cmd.args(["--color=always", "-v", "INFO", "tests/inputs/grep/b.txt"])
+        .assert()
+        .success()
+        .stdout("somefile.lua 16:22 [ERROR] message\n");
+
+    let mut cmd = Command::cargo_bin("grep")?;
+    cmd.args(["--color", "ERROR", "tests/inputs/grep/b.txt"])
+        .assert()
+        .success()
+        .stdout("somefile.lua 16:22 [ERROR] message\n");
+
+    Ok(())
+}
+
+#[test]
+fn grep_color_env() -> TestResult {
+    let mut cmd = Command::cargo_bin("grep")?;
+    cmd.args(["--colour=always", "ERROR", "tests/inputs/grep/b.txt"])
+        .env("GREP_COLORS", "ms=04:ne")
+        .assert()
+        .success()
+        .stdout("somefile.lua 16:22 [\x1b[04mERROR\x1b[m] message\n");
+
+    Ok(())
+}
+
+#[test]
+fn grep_only_matching() -> TestResult {
+    let mut cmd = Command::cargo_bin("grep")?;
+    cmd.args(["-o", "-n", "-b", "some[a-z]*", "tests/inputs/grep/a.txt"])
+        .assert()
+        .success()
+        .stdout("10:289:something\n10:309:something\n");
+
+    // Context lines are not printed, and -c still counts lines
+    let mut cmd = Command::cargo_bin("grep")?;
+    cmd.args(["-o", "-C", "1", "ERROR", "tests/inputs/grep/b.txt"])
+        .assert()
+        .success()
+        .stdout("ERROR\n");
+
+    let mut cmd = Command::cargo_bin("grep")?;
+    cmd.args(["-o", "-c", "some", "tests/inputs/grep/a.txt"])
+        .assert()
+        .success()
+        .stdout("1\n");
+
+    Ok(())
+}
+
+#[test]
+fn grep_word_regexp() -> TestResult {
+    let mut cmd = Command::cargo_bin("grep")?;
+    cmd.args(["-c", "-w", "some", "tests/inputs/grep/a.txt"])
+        .assert()
+        .failure()
+        .code(1)
+        .stdout("0\n");
+
+    let mut cmd = Command::cargo_bin("grep")?;
+    cmd.args(["-o", "-w", "-F", "INFO", "tests/inputs/grep/b.txt"])
+        .assert()
+        .success()
+        .stdout("INFO\nINFO\n");
+
+    Ok(())
+}
+
+#[test]
+fn grep_line_regexp() -> TestResult {
+    let mut cmd = Command::cargo_bin("grep")?;
+    cmd.args(["-x", "And.*land", "tests/inputs/grep/a.txt"])
+        .assert()
+        .success()
+        .stdout("And no one showed us to the land\n");
+
+    let mut cmd = Command::cargo_bin("grep")?;
+    cmd.args(["-x", "-F", "And", "tests/inputs/grep/a.txt"])
+        .assert()
+        .failure()
+        .code(1);
+
+    // The terminator is not part of the line
+    let mut cmd = Command::cargo_bin("grep")?;
+    cmd.args(["-c", "e$", "tests/inputs/grep/a.txt"])
+        .assert()
+        .success()
+        .stdout("2\n");
+
+    Ok(())
+}
+
+/// A directory tree with ignore files, hidden and binary files
+fn tree() -> Result<TempDir, Box<dyn std::error::Error>> {
+    let dir = temp_dir("grep")?;
+    for sub in ["build", "src", ".git", "vendor"] {
+        fs::create_dir_all(dir.join(sub))?;
+    }
+
+    fs::write(dir.join(".gitignore"), "build/\n")?;
+    fs::write(dir.join(".ignore"), "*.log\n")?;
+    fs::write(dir.join(".git/config"), "needle in git\n")?;
+    fs::write(dir.join("app.log"), "needle in log\n")?;
+    fs::write(dir.join("build/out.txt"), "needle in build\n")?;
+    fs::write(dir.join("data.bin"), "needle\0in binary\n")?;
+    fs::write(dir.join("notes.txt"), "needle in notes\n")?;
+    fs::write(dir.join("src/main.rs"), "// needle in main\n")?;
+    fs::write(dir.join("vendor/lib.rs"), "// needle in vendor\n")?;
+
+    Ok(dir)
+}
+
+#[test]
+fn grep_recursive_ignore_files() -> TestResult {
+    let dir = tree()?;
+
+    let mut cmd = Command::cargo_bin("grep")?;
+    cmd.current_dir(&dir)
+        .args(["-r", "needle"])
+        .assert()
+        .success()
+        .stdout(concat!(
+            "Binary file ./data.bin matches\n",
+            "./notes.txt:needle in notes\n",
+            "./src/main.rs:// needle in main\n",
+            "./vendor/lib.rs:// needle in vendor\n",
+        ));
+
+    let mut cmd = Command::cargo_bin("grep")?;
+    cmd.current_dir(&dir)
+        .args(["-r", "-l", "--no-ignore", "needle", "."])
+        .assert()
+        .success()
+        .stdout(concat!(
+            "./.git/config\n",
+            "./app.log\n",
+            "./build/out.txt\n",
+            "./data.bin\n",
+            "./notes.txt\n",
+            "./src/main.rs\n",
+            "./vendor/lib.rs\n",
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn grep_recursive_globs() -> TestResult {
+    let dir = tree()?;
+
+    let mut cmd = Command::cargo_bin("grep")?;
+    cmd.current_dir(&dir)
+        .args([
+            "-r",
+            "--include=*.rs",
+            "--exclude-dir=vendor",
+            "needle",
+            ".",
+        ])
+        .assert()
+        .success()
+        .stdout("./src/main.rs:// needle in main\n");
+
+    let mut cmd = Command::cargo_bin("grep")?;
+    cmd.current_dir(&dir)
+        .args([
+            "-r",
+            "-l",
+            "--exclude=*.txt",
+            "--exclude=*.bin",
+            "needle",
+            ".",
+        ])
+        .assert()
+        .success()
+        .stdout("./src/main.rs\n./vendor/lib.rs\n");
+
+    let mut cmd = Command::cargo_bin("grep")?;
+    cmd.args(["-r", "--include=[", "needle", "."])
+        .assert()
+        .failure()
+        .code(2);
+
+    Ok(())
+}
+
+#[test]
+fn grep_binary_files() -> TestResult {
+    let dir = tree()?;
+
+    let mut cmd = Command::cargo_bin("grep")?;
+    cmd.current_dir(&dir)
+        .args(["-a", "needle", "data.bin"])
+        .assert()
+        .success()
+        .stdout("needle\0in binary\n");
+
+    let mut cmd = Command::cargo_bin("grep")?;
+    cmd.current_dir(&dir)
+        .args(["-c", "needle", "data.bin"])
+        .assert()
+        .success()
+        .stdout("1\n");
+
+    let mut cmd = Command::cargo_bin("grep")?;
+    cmd.current_dir(&dir)
+        .args(["-I", "needle", "data.bin"])
+        .assert()
+        .failure()
+        .code(1)
+        .stdout("");
+
+    Ok(())
+}
+
+#[test]
+fn grep_recursive_threads() -> TestResult {
+    // The output does not depend on the number of threads
+    let mut cmd = Command::cargo_bin("grep")?;
+    let expected = cmd
+        .args(["--threads=1", "-r", "-n", "-C", "1", "fn", "src", "tests"])
+        .output()?
+        .stdout;
+
+    let mut cmd = Command::cargo_bin("grep")?;
+    cmd.args(["--threads=4", "-r", "-n", "-C", "1", "fn", "src", "tests"])
+        .assert()
+        .success()
+        .stdout(expected);
+
+    Ok(())
+}
+
+#[test]
+fn grep_json() -> TestResult {
+    let path = r#"{"text":"tests/inputs/grep/b.txt"}"#;
+    let expected = [
+        format!(r#"{{"type":"begin","data":{{"path":{path}}}}}"#),
+        format!(
+            r#"{{"type":"context","data":{{"path":{path},"lines":{{"text":"somefile.lua 16:22 [ERROR] message\n"}},"line_number":1,"absolute_offset":0,"submatches":[]}}}}"#
+        ),
+        format!(
+            r#"{{"type":"match","data":{{"path":{path},"lines":{{"text":"somefile.lua 12:23 [INFO] message\n"}},"line_number":2,"absolute_offset":35,"submatches":[{{"match":{{"text":"INFO"}},"start":20,"end":24}}]}}}}"#
+        ),
+        format!(
+            r#"{{"type":"end","data":{{"path":{path},"binary":false,"stats":{{"searches":1,"searches_with_match":1,"matched_lines":1,"matches":1}}}}}}"#
+        ),
+        r#"{"type":"summary","data":{"stats":{"searches":1,"searches_with_match":1,"matched_lines":1,"matches":1}}}"#.to_string(),
+    ];
+
+    let mut cmd = Command::cargo_bin("grep")?;
+    cmd.args([
+        "--json",
+        "-B",
+        "1",
+        "-m",
+        "1",
+        "INFO",
+        "tests/inputs/grep/b.txt",
+    ])
+    .assert()
+    .success()
+    .stdout(expected.join("\n") + "\n");
+
+    // Lines that are not valid UTF-8 are base64 encoded
+    let mut cmd = Command::cargo_bin("grep")?;
+    cmd.args(["--json", "x"])
+        .write_stdin(&b"caf\xe9 x\n"[..])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            r#""lines":{"bytes":"Y2Fm6SB4Cg=="},"line_number":1,"absolute_offset":0,"submatches":[{"match":{"text":"x"},"start":5,"end":6}]"#,
+        ));
+
+    let mut cmd = Command::cargo_bin("grep")?;
+    cmd.args(["--json", "-c", "x", "tests/inputs/grep/b.txt"])
+        .assert()
+        .failure()
+        .code(2);
+
+    Ok(())
+}
+
+#[test]
+fn grep_replace() -> TestResult {
+    let mut cmd = Command::cargo_bin("grep")?;
+    cmd.args([
+        "--replace",
+        "<$2:$1>",
+        r"(\d+):(\d+)",
+        "tests/inputs/grep/b.txt",
+    ])
+    .assert()
+    .success()
+    .stdout(concat!(
+        "somefile.lua <22:16> [ERROR] message\n",
+        "somefile.lua <23:12> [INFO] message\n",
+        "somefile.lua <01:13> [INFO] message\n",
+    ));
+
+    let mut cmd = Command::cargo_bin("grep")?;
+    cmd.args([
+        "-n",
+        "--replace",
+        "${level}!",
+        r"(?P<level>[A-Z]+)\]",
+        "tests/inputs/grep/b.txt",
+    ])
+    .assert()
+    .success()
+    .stdout(concat!(
+        "1:somefile.lua 16:22 [ERROR! message\n",
+        "2:somefile.lua 12:23 [INFO! message\n",
+        "3:somefile.lua 13:01 [INFO! message\n",
+    ));
+
+    let mut cmd = Command::cargo_bin("grep")?;
+    cmd.args([
+        "-o",
+        "-F",
+        "--replace",
+        "($0)",
+        "INFO",
+        "tests/inputs/grep/b.txt",
+    ])
+    .assert()
+    .success()
+    .stdout("(INFO)\n(INFO)\n");
+
+    Ok(())
+}
+
+#[test]
+fn grep_replace_in_place() -> TestResult {
+    let dir = temp_dir("grep")?;
+    fs::write(dir.join("a.txt"), "f(1, 2)\ng(3)\nf(4, 5) f(6, 7)\n")?;
+    fs::write(dir.join("b.txt"), "nothing\n")?;
+
+    let mut cmd = Command::cargo_bin("grep")?;
+    cmd.current_dir(&dir)
+        .args([
+            "-r",
+            "--in-place",
+            "--replace",
+            "f($2, $1)",
+            r"f\((\d), (\d)\)",
+        ])
+        .assert()
+        .success()
+        .stdout("./a.txt:3\n");
+    assert_eq!(
+        fs::read_to_string(dir.join("a.txt"))?,
+        "f(2, 1)\ng(3)\nf(5, 4) f(7, 6)\n"
+    );
+    assert_eq!(fs::read_to_string(dir.join("b.txt"))?, "nothing\n");
+
+    // No temporary file is left behind
+    assert_eq!(fs::read_dir(&dir)?.count(), 2);
+
+    let mut cmd = Command::cargo_bin("grep")?;
+    cmd.args(["--in-place", "--replace", "x", "y"])
+        .write_stdin("y\n")
+        .assert()
+        .failure()
+        .code(2)
+        .stderr("grep: --in-place cannot rewrite standard input\n");
+
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn grep_replace_through_symlink() -> TestResult {
+    let dir = temp_dir("grep")?;
+    fs::write(dir.join("target.txt"), "old\n")?;
+    std::os::unix::fs::symlink("target.txt", dir.join("link.txt"))?;
+
+    let mut cmd = Command::cargo_bin("grep")?;
+    cmd.current_dir(&dir)
+        .args(["--in-place", "--replace", "new", "old", "link.txt"])
+        .assert()
+        .success();
+    assert!(fs::symlink_metadata(dir.join("link.txt"))?
+        .file_type()
+        .is_symlink());
+    assert_eq!(fs::read_to_string(dir.join("target.txt"))?, "new\n");
+    assert_eq!(fs::read_dir(&dir)?.count(), 2);
+
+    Ok(())
+}
+
+#[test]
+fn grep_multiline() -> TestResult {
+    let input = "at main\nError: boom\n  at run\n  at main\nok\nError: again\n  at exit\n";
+
+    let mut cmd = Command::cargo_bin("grep")?;
+    cmd.args(["-U", "-n", r"(?s)Error: \w+\n.*?at main"])
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout("2:Error: boom\n3:  at run\n4:  at main\n");
+
+    // Without -U, patterns only see one line at a time
+    let mut cmd = Command::cargo_bin("grep")?;
+    cmd.args(["-c", r"boom\n"])
+        .write_stdin(input)
+        .assert()
+        .failure()
+        .code(1)
+        .stdout("0\n");
+
+    // ^ and $ match at line boundaries
+    let mut cmd = Command::cargo_bin("grep")?;
+    cmd.args(["-U", "-n", "-A", "1", r"^Error: again$\n"])
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout("6:Error: again\n7-  at exit\n");
+
+    // The parts of a match on every line it spans are printed
+    let input = "x\n  at y\nz\n";
+    let mut cmd = Command::cargo_bin("grep")?;
+    cmd.args(["-U", "-o", "-b", r"x\n  at"])
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout("0:x\n2:  at\n");
+
+    let mut cmd = Command::cargo_bin("grep")?;
+    cmd.args(["-U", "--color=always", r"x\n  at"])
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout(concat!(
+            "\x1b[01;31m\x1b[Kx\x1b[m\x1b[K\n",
+            "\x1b[01;31m\x1b[K  at\x1b[m\x1b[K y\n"
+        ));
+
+    let mut cmd = Command::cargo_bin("grep")?;
+    cmd.args(["-U", "--json", r"x\n  at"])
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            r#""submatches":[{"match":{"text":"x"},"start":0,"end":1}]"#,
+        ))
+        .stdout(predicate::str::contains(
+            r#""submatches":[{"match":{"text":"  at"},"start":0,"end":4}]"#,
+        ));
+
+    // The replacement goes where the match starts, as with --in-place
+    let mut cmd = Command::cargo_bin("grep")?;
+    cmd.args(["-U", "--replace", "X", r"x\n  at"])
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout("X\n y\n");
+
+    let mut cmd = Command::cargo_bin("grep")?;
+    cmd.args(["-U", "-o", "--replace", "X", r"x\n  at"])
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout("X\n");
+
+    Ok(())
+}
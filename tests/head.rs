@@ -22,7 +22,7 @@ fn head_skips_missing_file() -> TestResult {
     let mut cmd = Command::cargo_bin("head")?;
     cmd.args(["missing.txt"])
         .assert()
-        .success()
+        .failure()
         .stderr(predicate::str::contains("No such file"));
 
     Ok(())
@@ -69,7 +69,7 @@ fn head_handles_errors() -> TestResult {
     let mut cmd = Command::cargo_bin("head")?;
     cmd.args(["-n", "4", "tests/inputs/head_input.txt", "missing"])
         .assert()
-        .success()
+        .failure()
         .stderr(predicate::str::contains("No such file"));
 
     Ok(())
@@ -91,3 +91,14 @@ fn head_handles_multiple() -> TestResult {
 
     Ok(())
 }
+
+#[test]
+fn head_zero_terminated() -> TestResult {
+    let mut cmd = Command::cargo_bin("head")?;
+    cmd.args(["-z", "-n", "2", "tests/inputs/zero_terminated.txt"])
+        .assert()
+        .success()
+        .stdout("one\0two\nlines\0");
+
+    Ok(())
+}
@@ -0,0 +1,185 @@
+use assert_cmd::Command;
+use predicates::prelude::*;
+use std::fs;
+
+mod common;
+
+use common::*;
+
+#[test]
+fn runix_lists_tools() -> TestResult {
+    let mut cmd = Command::cargo_bin("runix")?;
+    cmd.args(["--list"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("grep\n").and(predicate::str::contains("cut\n")));
+
+    Ok(())
+}
+
+#[test]
+fn runix_requires_tool() -> TestResult {
+    let mut cmd = Command::cargo_bin("runix")?;
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("missing tool name"));
+
+    Ok(())
+}
+
+#[test]
+fn runix_dispatches_by_argument() -> TestResult {
+    let mut cmd = Command::cargo_bin("runix")?;
+    let expected = fs::read_to_string("tests/inputs/grep_insensitive_expected.txt")?;
+    cmd.args(["grep", "-i", "error", "tests/inputs/grep/b.txt"])
+        .assert()
+        .success()
+        .stdout(expected);
+
+    Ok(())
+}
+
+#[test]
+fn runix_dispatches_by_argv0() -> TestResult {
+    let dir = temp_dir("runix")?;
+
+    let mut cmd = Command::cargo_bin("runix")?;
+    cmd.arg("--install").arg(&dir).assert().success();
+
+    let mut cmd = Command::new(dir.join("echo"));
+    cmd.args(["hello", "world"])
+        .assert()
+        .success()
+        .stdout("hello world\n");
+
+    Command::new(dir.join("false")).assert().failure();
+
+    Ok(())
+}
+
+#[test]
+fn runix_man_page() -> TestResult {
+    let mut cmd = Command::cargo_bin("runix")?;
+    cmd.args(["man", "grep"]).assert().success().stdout(
+        predicate::str::contains(".TH grep 1").and(predicate::str::contains("ignore\\-case")),
+    );
+
+    let mut cmd = Command::cargo_bin("runix")?;
+    cmd.args(["man", "nope"])
+        .assert()
+        .failure()
+        .stderr("runix: unknown tool 'nope', see `runix --list`\n");
+
+    Ok(())
+}
+
+#[test]
+fn runix_completions() -> TestResult {
+    let mut cmd = Command::cargo_bin("runix")?;
+    cmd.args(["completions", "bash", "cut"])
+        .assert()
+        .success()
+        .stdout(
+            predicate::str::contains("complete -F _cut").and(predicate::str::contains("--fields")),
+        );
+
+    let mut cmd = Command::cargo_bin("runix")?;
+    cmd.args(["completions", "fish"]).assert().success().stdout(
+        predicate::str::contains("complete -c wc")
+            .and(predicate::str::contains("complete -c echo")),
+    );
+
+    Ok(())
+}
+
+#[test]
+fn runix_pipe() -> TestResult {
+    let mut cmd = Command::cargo_bin("runix")?;
+    cmd.args([
+        "pipe",
+        "cat tests/inputs/grep/b.txt | grep -i 'error' | wc -l",
+    ])
+    .assert()
+    .success()
+    .stdout("    1 \n");
+
+    let mut cmd = Command::cargo_bin("runix")?;
+    cmd.args(["pipe", "echo \"a b\" 'c d' | cat -n"])
+        .assert()
+        .success()
+        .stdout("     1\ta b c d\n");
+
+    let mut cmd = Command::cargo_bin("runix")?;
+    cmd.args([
+        "pipe",
+        "cat tests/inputs/tail_input.txt | tail -n 2 | wc -l",
+    ])
+    .assert()
+    .success()
+    .stdout("    2 \n");
+
+    Ok(())
+}
+
+// Runs in a terminal made by util-linux script, skipped without it
+#[cfg(target_os = "linux")]
+#[test]
+fn runix_pipe_stage_is_not_a_terminal() -> TestResult {
+    let pipeline = format!(
+        "{} pipe 'grep --color=auto ERROR tests/inputs/grep/b.txt | cat'",
+        assert_cmd::cargo::cargo_bin("runix").display()
+    );
+    let Ok(output) = std::process::Command::new("script")
+        .args(["-qec", &pipeline, "/dev/null"])
+        .output()
+    else {
+        return Ok(());
+    };
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout)?;
+    assert_eq!(stdout, "somefile.lua 16:22 [ERROR] message\r\n");
+
+    Ok(())
+}
+
+#[test]
+fn runix_pipe_stops_early() -> TestResult {
+    let dir = temp_dir("runix")?;
+    let path = dir.join("lines.txt");
+    fs::write(&path, "line\n".repeat(1_000_000))?;
+
+    let mut cmd = Command::cargo_bin("runix")?;
+    cmd.arg("pipe")
+        .arg(format!("cat {} | head -n 2", path.display()))
+        .assert()
+        .success()
+        .stdout("line\nline\n")
+        .stderr("");
+
+    Ok(())
+}
+
+#[test]
+fn runix_pipe_status() -> TestResult {
+    let mut cmd = Command::cargo_bin("runix")?;
+    cmd.args(["pipe", "cat tests/inputs/grep/b.txt | grep nothing-matches"])
+        .assert()
+        .code(1)
+        .stdout("");
+
+    let mut cmd = Command::cargo_bin("runix")?;
+    cmd.args(["pipe", "cat missing.txt | wc -l"])
+        .assert()
+        .success()
+        .stdout("    0 \n")
+        .stderr("cat: missing.txt: No such file or directory\n");
+
+    let mut cmd = Command::cargo_bin("runix")?;
+    cmd.args(["pipe", "cat 'x | wc"])
+        .assert()
+        .failure()
+        .stderr("runix: unterminated quote in pipeline\n");
+
+    Ok(())
+}
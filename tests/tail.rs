@@ -149,3 +149,33 @@ fn tail_bytes_zero_plus() -> TestResult {
 
     Ok(())
 }
+
+#[test]
+fn tail_zero_terminated() -> TestResult {
+    let mut cmd = Command::cargo_bin("tail")?;
+    cmd.args(["-z", "-n", "2", "tests/inputs/zero_terminated.txt"])
+        .assert()
+        .success()
+        .stdout("two\nlines\0three\0");
+
+    Ok(())
+}
+
+#[test]
+fn tail_stdin() -> TestResult {
+    let mut cmd = Command::cargo_bin("tail")?;
+    cmd.args(["-n", "2"])
+        .write_stdin("one\ntwo\nthree\n")
+        .assert()
+        .success()
+        .stdout("two\nthree\n");
+
+    let mut cmd = Command::cargo_bin("tail")?;
+    cmd.args(["-c", "4", "-"])
+        .write_stdin("one\ntwo\nthree\n")
+        .assert()
+        .success()
+        .stdout("ree\n");
+
+    Ok(())
+}
@@ -77,3 +77,15 @@ fn uniq_stdin() -> TestResult {
 
     Ok(())
 }
+
+#[test]
+fn uniq_zero_terminated() -> TestResult {
+    let mut cmd = Command::cargo_bin("uniq")?;
+    cmd.args(["-z", "-c"])
+        .write_stdin("a\nb\0a\nb\0c\0")
+        .assert()
+        .success()
+        .stdout("      2 a\nb\0      1 c\0");
+
+    Ok(())
+}
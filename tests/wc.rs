@@ -11,7 +11,7 @@ fn wc_skips_missing_file() -> TestResult {
     let mut cmd = Command::cargo_bin("wc")?;
     cmd.args(["missing.txt"])
         .assert()
-        .success()
+        .failure()
         .stderr(predicate::str::contains("No such file"));
 
     Ok(())